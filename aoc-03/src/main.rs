@@ -1,7 +1,20 @@
+// The original code predates clippy's style lints.
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
 use std::io;
 use std::io::prelude::*;
 use std::cmp::Ordering;
 use std::ops::Add;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::process;
+
+mod mmap;
+mod render;
+mod report;
+mod route;
+mod tui;
 
 #[derive(Eq)]
 struct Point {
@@ -43,6 +56,14 @@ impl PartialEq for Point {
     }
 }
 
+// Anything find_crashes() can ride over. The map repeats to the right, so
+// has_tree() takes any x.
+trait Grid {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn has_tree(&self, x : usize, y : usize) -> bool;
+}
+
 struct Map {
     map : Vec<Point>,
     width : usize,
@@ -76,7 +97,7 @@ impl Map {
     fn has_tree(&self, x : usize, y : usize) -> bool {
         let p = Point { x: x % self.width, y: y };
         match self.map.binary_search(&p) {
-            Ok(m) => {
+            Ok(_) => {
                 return true;
             }
             _ => {
@@ -86,49 +107,144 @@ impl Map {
     }
 }
 
-fn get_input() -> Map {
-    let stdin = io::stdin();
+impl Grid for Map {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn has_tree(&self, x : usize, y : usize) -> bool {
+        Map::has_tree(self, x, y)
+    }
+}
+
+#[derive(Debug)]
+enum MapError {
+    Io(io::Error),
+    Empty,
+    // Line numbers are 1-based, as shown in an editor.
+    RaggedRow { line : usize, expected : usize, found : usize },
+    UnknownChar { line : usize, col : usize, c : char },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Io(e) => write!(f, "read error: {}", e),
+            MapError::Empty => write!(f, "map is empty"),
+            MapError::RaggedRow { line, expected, found } => {
+                write!(f, "line {}: expected {} columns, found {}", line, expected, found)
+            }
+            MapError::UnknownChar { line, col, c } => {
+                write!(f, "line {}, column {}: unknown character {:?}", line, col, c)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for MapError {
+    fn from(e : io::Error) -> Self {
+        MapError::Io(e)
+    }
+}
+
+// Reads rows of '.' and '#' until EOF or the first blank line. Every row must
+// be as wide as the first one.
+fn parse_map<R : BufRead>(reader : R) -> Result<Map, MapError> {
     let mut map = Map::new();
     let mut width = 0;
     let mut height = 0;
 
-    for (i, line) in stdin.lock().lines().enumerate() {
-        let l = line.unwrap();
-        for (j, c) in l.chars().enumerate() {
-            if c == '#' {
-                map.add_tree(j, i);
-            }
+    for (i, line) in reader.lines().enumerate() {
+        let l = line?;
+        let l = l.trim_end_matches('\r');
+        if l.is_empty() {
+            break;
         }
 
-        if l.len() > width {
-            width = l.len();
-        } else if l.len() == 0 {
-            break;
+        let len = l.chars().count();
+        if height == 0 {
+            width = len;
+        } else if len != width {
+            return Err(MapError::RaggedRow { line: i + 1, expected: width, found: len });
+        }
+
+        for (j, c) in l.chars().enumerate() {
+            match c {
+                '#' => map.add_tree(j, i),
+                '.' => {}
+                _ => return Err(MapError::UnknownChar { line: i + 1, col: j + 1, c }),
+            }
         }
 
         height += 1;
     }
 
+    if height == 0 {
+        return Err(MapError::Empty);
+    }
+
     map.set_dim(width, height);
     map.sort();
 
-    return map;
+    Ok(map)
 }
 
-fn find_crashes(map : &Map, start : (usize, usize), step : (usize, usize)) -> i64 {
-    let mut p = Point { x: start.0, y: start.1 };
-    let p_inc = Point { x: step.0, y: step.1 };
-    let mut res = 0;
-    while p.y <= map.height {
-        if map.has_tree(p.x, p.y) {
-            res += 1;
+fn get_input() -> Map {
+    let stdin = io::stdin();
+    match parse_map(stdin.lock()) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Invalid map: {}", e);
+            process::exit(1);
         }
-        p.x += p_inc.x;
-        p.y += p_inc.y;
     }
-    return res;
 }
 
+// How many positions a ride from start visits before it leaves the bottom
+// of the map.
+fn visits<G : Grid + ?Sized>(map : &G, start : (usize, usize), step : (usize, usize)) -> usize {
+    let height = map.height();
+    if start.1 >= height {
+        0
+    } else {
+        // A step that never goes down only visits the start.
+        (height - 1 - start.1).checked_div(step.1).map_or(1, |n| n + 1)
+    }
+}
+
+// Every position visited from start to the bottom of the map, unwrapped
+// horizontally so x keeps growing past the map width. The path ends early
+// if x no longer fits in a usize.
+fn walk<G : Grid + ?Sized>(map : &G, start : (usize, usize), step : (usize, usize)) -> impl Iterator<Item = Point> {
+    (0..visits(map, start, step)).map_while(move |i| {
+        let x = i.checked_mul(step.0)?.checked_add(start.0)?;
+        Some(Point { x: x, y: start.1 + i * step.1 })
+    })
+}
+
+// Same positions as walk(), but with x kept modulo the map width, which is
+// all has_tree() looks at, so any step rides to the bottom.
+fn walk_wrapped<G : Grid + ?Sized>(map : &G, start : (usize, usize), step : (usize, usize)) -> impl Iterator<Item = Point> {
+    let width = map.width();
+    let mut x = start.0 % width;
+    (0..visits(map, start, step)).map(move |i| {
+        let p = Point { x: x, y: start.1 + i * step.1 };
+        x = (x + step.0 % width) % width;
+        p
+    })
+}
+
+fn find_crashes<G : Grid + ?Sized>(map : &G, start : (usize, usize), step : (usize, usize)) -> i64 {
+    walk_wrapped(map, start, step).filter(|p| map.has_tree(p.x, p.y)).count() as i64
+}
+
+// The slopes from part 2, used whenever no slopes are given.
+const SLOPES : [(usize, usize); 5] = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+
 fn part1() {
     let map = get_input();
     let start = (0, 0);
@@ -137,40 +253,175 @@ fn part1() {
     println!("Crashes: {}", res);
 }
 
-fn part2() {
+// report [--json] [--mmap FILE] [right,down ...]
+fn part2(args : &[String]) {
+    let mut json = false;
+    let mut file = None;
+    let mut slopes = Vec::new();
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        match a.as_str() {
+            "--json" => json = true,
+            "--mmap" => match it.next() {
+                Some(f) if !f.starts_with("--") => file = Some(f),
+                _ => {
+                    eprintln!("--mmap: expected a file name");
+                    process::exit(1);
+                }
+            },
+            _ => slopes.push(parse_slope(a)),
+        }
+    }
+    if slopes.is_empty() {
+        slopes = SLOPES.to_vec();
+    }
+
+    let report = match file {
+        Some(f) => match mmap::MappedMap::open(f) {
+            Ok(map) => mmap::report(&map, (0, 0), &slopes),
+            Err(e) => {
+                eprintln!("Invalid map {}: {}", f, e);
+                process::exit(1);
+            }
+        },
+        None => report::Report::new(&get_input(), (0, 0), &slopes),
+    };
+    if json {
+        println!("{}", report.json());
+    } else {
+        print!("{}", report.table());
+    }
+}
+
+fn parse_num(arg : Option<&str>, default : usize) -> usize {
+    match arg {
+        Some(a) => a.parse().unwrap_or_else(|_| {
+            eprintln!("Not a number: {}", a);
+            process::exit(1);
+        }),
+        None => default,
+    }
+}
+
+// "right,down", down defaulting to 1.
+fn parse_slope(arg : &str) -> (usize, usize) {
+    let mut parts = arg.split(',');
+    (parse_num(parts.next(), 0), parse_num(parts.next(), 1))
+}
+
+// render [right] [down] [--color] [--ppm FILE [--scale N]]
+fn draw(args : &[String]) {
+    let mut nums = Vec::new();
+    let mut colour = false;
+    let mut ppm = None;
+    let mut scale = 4;
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        match a.as_str() {
+            "--color" => colour = true,
+            "--ppm" => match it.next() {
+                Some(file) if !file.starts_with("--") => ppm = Some(file),
+                _ => {
+                    eprintln!("--ppm: expected a file name");
+                    process::exit(1);
+                }
+            },
+            "--scale" => scale = parse_num(it.next().map(|s| s.as_str()), scale),
+            _ => nums.push(a.as_str()),
+        }
+    }
+    let step = (parse_num(nums.first().copied(), 3), parse_num(nums.get(1).copied(), 1));
+
     let map = get_input();
-    let start = (0, 0);
+    let path : Vec<Point> = walk(&map, (0, 0), step).collect();
+    let cells = render::cells(&map, &path);
+    match ppm {
+        Some(file) => {
+            let res = fs::File::create(file)
+                .and_then(|f| render::write_ppm(&cells, scale, io::BufWriter::new(f)));
+            if let Err(e) = res {
+                eprintln!("Could not write {}: {}", file, e);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", render::ascii(&cells, colour)),
+    }
+}
 
-    let step1 = (1, 1);
-    let res1 = find_crashes(&map, start, step1);
-    println!("Crashes 1: {}", res1);
-    let step2 = (3, 1);
-    let res2 = find_crashes(&map, start, step2);
-    println!("Crashes 2: {}", res2);
-    let step3 = (5, 1);
-    let res3 = find_crashes(&map, start, step3);
-    println!("Crashes 3: {}", res3);
-    let step4 = (7, 1);
-    let res4 = find_crashes(&map, start, step4);
-    println!("Crashes 4: {}", res4);
-    let step5 = (1, 2);
-    let res5 = find_crashes(&map, start, step5);
-    println!("Crashes 5: {}", res5);
-
-    let prod = res1*res2*res3*res4*res5;
-    println!("{}*{}*{}*{}*{} = {}", res1, res2, res3, res4, res5, prod);
+// route [min right] [max right] [down] [--color]
+fn search(args : &[String]) {
+    let colour = args.iter().any(|a| a == "--color");
+    let nums : Vec<&str> = args.iter().map(|a| a.as_str()).filter(|a| !a.starts_with("--")).collect();
+    let min_right = parse_num(nums.first().copied(), 1);
+    let max_right = parse_num(nums.get(1).copied(), 3);
+    let down = parse_num(nums.get(2).copied(), 1);
+
+    let map = get_input();
+    let moves = route::slopes(min_right..=max_right, down);
+    match route::least_crashes(&map, (0, 0), &moves) {
+        Some(r) => {
+            print!("{}", render::ascii(&render::cells(&map, &r.path), colour));
+            println!("Crashes: {}", r.cost);
+        }
+        None => {
+            eprintln!("No route with right {}..={}, down {}", min_right, max_right, down);
+            process::exit(1);
+        }
+    }
+}
+
+// tui [right,down ...]
+fn animate(args : &[String]) {
+    let mut slopes : Vec<(usize, usize)> = args.iter().map(|a| parse_slope(a)).collect();
+    if slopes.is_empty() {
+        slopes = SLOPES.to_vec();
+    }
+
+    let map = get_input();
+    if let Err(e) = tui::run(&map, &slopes) {
+        eprintln!("Terminal error: {}", e);
+        process::exit(1);
+    }
 }
 
 fn main() {
-    println!("Enter map...");
-    //part1();
-    part2();
+    let args : Vec<String> = env::args().skip(1).collect();
+    if !args.iter().any(|a| a == "--mmap") {
+        eprintln!("Enter map...");
+    }
+    match args.first().map(|a| a.as_str()) {
+        Some("1") => part1(),
+        Some("render") => draw(&args[1..]),
+        Some("route") => search(&args[1..]),
+        Some("report") => part2(&args[1..]),
+        Some("tui") => animate(&args[1..]),
+        _ => part2(&[]),
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
+    pub const EXAMPLE : &str = "\
+..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#
+";
+
+    fn example() -> Map {
+        parse_map(EXAMPLE.as_bytes()).unwrap()
+    }
+
     #[test]
     fn test_tree() {
         let mut m = Map::new();
@@ -194,4 +445,93 @@ mod tests {
         assert_eq!(m.has_tree(4, 2), true);
         assert_eq!(m.has_tree(7, 2), true);
     }
+
+    #[test]
+    fn test_parse_example() {
+        let m = example();
+        assert_eq!(m.width, 11);
+        assert_eq!(m.height, 11);
+        assert!(m.has_tree(2, 0));
+        assert!(!m.has_tree(0, 0));
+        assert!(m.has_tree(10, 10));
+    }
+
+    #[test]
+    fn test_parse_stops_at_blank_line() {
+        let m = parse_map("..#\n#..\n\n###\n".as_bytes()).unwrap();
+        assert_eq!(m.height, 2);
+        assert!(!m.has_tree(1, 3));
+    }
+
+    #[test]
+    fn test_parse_crlf() {
+        let m = parse_map("..#\r\n#..\r\n".as_bytes()).unwrap();
+        assert_eq!(m.width, 3);
+        assert_eq!(m.height, 2);
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert!(matches!(parse_map("".as_bytes()), Err(MapError::Empty)));
+        assert!(matches!(parse_map("\n..#\n".as_bytes()), Err(MapError::Empty)));
+    }
+
+    #[test]
+    fn test_parse_ragged() {
+        let res = parse_map("..#\n#..\n#.\n".as_bytes());
+        assert!(matches!(res, Err(MapError::RaggedRow { line: 3, expected: 3, found: 2 })));
+        let res = parse_map("..#\n#...\n".as_bytes());
+        assert!(matches!(res, Err(MapError::RaggedRow { line: 2, expected: 3, found: 4 })));
+    }
+
+    #[test]
+    fn test_parse_unknown_char() {
+        let res = parse_map("..#\n#X.\n".as_bytes());
+        assert!(matches!(res, Err(MapError::UnknownChar { line: 2, col: 2, c: 'X' })));
+    }
+
+    #[test]
+    fn test_crashes_example() {
+        let m = example();
+        assert_eq!(find_crashes(&m, (0, 0), (1, 1)), 2);
+        assert_eq!(find_crashes(&m, (0, 0), (3, 1)), 7);
+        assert_eq!(find_crashes(&m, (0, 0), (5, 1)), 3);
+        assert_eq!(find_crashes(&m, (0, 0), (7, 1)), 4);
+        assert_eq!(find_crashes(&m, (0, 0), (1, 2)), 2);
+    }
+
+    #[test]
+    fn test_walk() {
+        let m = example();
+        let path : Vec<Point> = walk(&m, (0, 0), (3, 1)).collect();
+        assert_eq!(path.len(), 11);
+        assert!(path[10] == Point { x: 30, y: 10 });
+        assert_eq!(walk(&m, (0, 0), (1, 2)).count(), 6);
+        assert_eq!(walk(&m, (0, 0), (1, 0)).count(), 1);
+        assert_eq!(walk(&m, (0, 11), (1, 1)).count(), 0);
+    }
+
+    #[test]
+    fn test_walk_overflow() {
+        let m = parse_map("..#\n#..\n...\n.#.\n".as_bytes()).unwrap();
+        // The third position would be past usize::MAX.
+        assert_eq!(walk(&m, (0, 0), (usize::MAX, 1)).count(), 2);
+        let wrapped : Vec<Point> = walk_wrapped(&m, (0, 0), (usize::MAX / 2, 1)).collect();
+        assert_eq!(wrapped.len(), 4);
+        assert!(wrapped[3] == Point { x: 0, y: 3 });
+        // usize::MAX is a multiple of 3, so that ride stays in column 0.
+        assert_eq!(find_crashes(&m, (0, 0), (usize::MAX, 1)), 1);
+        assert_eq!(find_crashes(&m, (0, 0), (usize::MAX / 2, 1)), 0);
+        assert_eq!(find_crashes(&m, (2, 0), (usize::MAX / 2, 1)), 2);
+    }
+
+    #[test]
+    fn test_crashes_stay_inside_map() {
+        // A tree directly below the last row must not be counted.
+        let mut m = Map::new();
+        m.add_tree(0, 2);
+        m.set_dim(1, 2);
+        m.sort();
+        assert_eq!(find_crashes(&m, (0, 0), (0, 1)), 0);
+    }
 }
@@ -0,0 +1,193 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::str;
+use std::thread;
+
+use memmap2::Mmap;
+
+use crate::report::{Report, Rider};
+use crate::{Grid, MapError};
+
+// A map read straight from a memory-mapped file instead of being collected
+// into a Vec of trees. Only the start offset of each row is kept in memory.
+pub struct MappedMap {
+    data : Mmap,
+    rows : Vec<usize>,
+    width : usize,
+}
+
+impl MappedMap {
+    pub fn open<P : AsRef<Path>>(path : P) -> Result<MappedMap, MapError> {
+        let file = File::open(path)?;
+        // Safety: the file must not be truncated while it is mapped. The
+        // inputs are generated once and only read afterwards.
+        let data = unsafe { Mmap::map(&file)? };
+        MappedMap::index(data)
+    }
+
+    // Same rules as parse_map(): rows of '.' and '#' of equal width, ending
+    // at EOF or the first blank line.
+    fn index(data : Mmap) -> Result<MappedMap, MapError> {
+        let mut rows = Vec::new();
+        let mut width = 0;
+        let mut start = 0;
+
+        while start < data.len() {
+            let end = data[start..].iter().position(|&b| b == b'\n').map_or(data.len(), |i| start + i);
+            let mut row = &data[start..end];
+            if row.last() == Some(&b'\r') {
+                row = &row[..row.len() - 1];
+            }
+            if row.is_empty() {
+                break;
+            }
+
+            let line = rows.len() + 1;
+            // A row with anything but '.' and '#' is about to fail, so it is
+            // read as text to report it in characters, like parse_map().
+            if row.iter().any(|&b| b != b'.' && b != b'#') {
+                return Err(bad_row(row, line, if rows.is_empty() { None } else { Some(width) }));
+            }
+            if rows.is_empty() {
+                width = row.len();
+            } else if row.len() != width {
+                return Err(MapError::RaggedRow { line, expected: width, found: row.len() });
+            }
+
+            rows.push(start);
+            start = end + 1;
+        }
+
+        if rows.is_empty() {
+            return Err(MapError::Empty);
+        }
+
+        Ok(MappedMap { data, rows, width })
+    }
+}
+
+// The error parse_map() gives for a row that is not all '.' and '#'. width
+// is None on the first row.
+fn bad_row(row : &[u8], line : usize, width : Option<usize>) -> MapError {
+    let text = match str::from_utf8(row) {
+        Ok(t) => t,
+        Err(_) => return MapError::Io(io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8")),
+    };
+    let len = text.chars().count();
+    match width {
+        Some(w) if w != len => MapError::RaggedRow { line, expected: w, found: len },
+        _ => {
+            let (col, c) = text.chars().enumerate().find(|&(_, c)| c != '.' && c != '#').unwrap();
+            MapError::UnknownChar { line, col: col + 1, c }
+        }
+    }
+}
+
+impl Grid for MappedMap {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn has_tree(&self, x : usize, y : usize) -> bool {
+        self.data[self.rows[y] + x % self.width] == b'#'
+    }
+}
+
+// Rides every slope on its own thread, all reading the same mapping.
+pub fn report(map : &MappedMap, start : (usize, usize), slopes : &[(usize, usize)]) -> Report {
+    let riders = thread::scope(|s| {
+        let handles : Vec<_> = slopes.iter()
+            .map(|&step| s.spawn(move || Rider::new(map, start, step)))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    Report { riders }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_crashes, parse_map};
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+
+    fn temp_file(name : &str, contents : &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("aoc-03-{}-{}", process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_matches_parse_map() {
+        let path = temp_file("example", crate::tests::EXAMPLE);
+        let mapped = MappedMap::open(&path).unwrap();
+        let map = parse_map(crate::tests::EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(mapped.height(), 11);
+        for y in 0..11 {
+            for x in 0..30 {
+                assert_eq!(Grid::has_tree(&mapped, x, y), map.has_tree(x, y));
+            }
+        }
+        assert_eq!(find_crashes(&mapped, (0, 0), (3, 1)), 7);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_report() {
+        let path = temp_file("report", crate::tests::EXAMPLE);
+        let mapped = MappedMap::open(&path).unwrap();
+        let r = report(&mapped, (0, 0), &[(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]);
+        let crashes : Vec<u64> = r.riders.iter().map(|r| r.crashes).collect();
+        assert_eq!(crashes, vec![2, 7, 3, 4, 2]);
+        assert_eq!(r.product(), Some(336));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_crlf_and_blank_line() {
+        let path = temp_file("crlf", "..#\r\n#..\r\n\r\n###\r\n");
+        let mapped = MappedMap::open(&path).unwrap();
+        assert_eq!(mapped.height(), 2);
+        assert!(Grid::has_tree(&mapped, 2, 0));
+        assert!(Grid::has_tree(&mapped, 3, 1));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_errors() {
+        let path = temp_file("ragged", "..#\n#.\n");
+        let res = MappedMap::open(&path);
+        assert!(matches!(res, Err(MapError::RaggedRow { line: 2, expected: 3, found: 2 })));
+        fs::remove_file(path).unwrap();
+
+        let path = temp_file("unknown", "..#\n#o.\n");
+        let res = MappedMap::open(&path);
+        assert!(matches!(res, Err(MapError::UnknownChar { line: 2, col: 2, c: 'o' })));
+        fs::remove_file(path).unwrap();
+
+        // Errors are the same as without the mapping, counted in characters.
+        for (i, text) in ["..#\n#\u{e9}.\n", "..#\n#\u{e9}...\n", "\u{e9}\n", "..\n.\t\n"].iter().enumerate() {
+            let path = temp_file(&format!("chars-{}", i), text);
+            let mapped = MappedMap::open(&path).err().unwrap().to_string();
+            assert_eq!(mapped, parse_map(text.as_bytes()).err().unwrap().to_string(), "{:?}", text);
+            fs::remove_file(path).unwrap();
+        }
+        let path = temp_file("utf8", "");
+        fs::write(&path, b"..\n.\xff\n").unwrap();
+        assert!(matches!(MappedMap::open(&path), Err(MapError::Io(_))));
+        fs::remove_file(path).unwrap();
+
+        let path = temp_file("empty", "");
+        assert!(matches!(MappedMap::open(&path), Err(MapError::Empty)));
+        fs::remove_file(path).unwrap();
+
+        assert!(matches!(MappedMap::open("/nonexistent/aoc-03"), Err(MapError::Io(_))));
+    }
+}
@@ -0,0 +1,133 @@
+use std::io;
+use std::io::prelude::*;
+
+use crate::{Map, Point};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cell {
+    Open,
+    Tree,
+    // Visited and hit a tree.
+    Crash,
+    // Visited without hitting anything.
+    Clear,
+}
+
+impl Cell {
+    fn symbol(self) -> char {
+        match self {
+            Cell::Open => '.',
+            Cell::Tree => '#',
+            Cell::Crash => 'X',
+            Cell::Clear => 'O',
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            Cell::Open => "\x1b[2m",
+            Cell::Tree => "\x1b[32m",
+            Cell::Crash => "\x1b[1;31m",
+            Cell::Clear => "\x1b[1;36m",
+        }
+    }
+
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Cell::Open => [240, 240, 240],
+            Cell::Tree => [34, 110, 40],
+            Cell::Crash => [220, 30, 30],
+            Cell::Clear => [40, 110, 230],
+        }
+    }
+}
+
+// Lays out the map with the path drawn on top of it. The map is repeated to
+// the right as many times as needed to cover the rightmost visited point.
+pub fn cells(map : &Map, path : &[Point]) -> Vec<Vec<Cell>> {
+    let max_x = path.iter().map(|p| p.x).max().unwrap_or(0);
+    let tiles = max_x / map.width + 1;
+    let mut grid = Vec::with_capacity(map.height);
+    for y in 0..map.height {
+        let row : Vec<Cell> = (0..map.width * tiles)
+            .map(|x| if map.has_tree(x, y) { Cell::Tree } else { Cell::Open })
+            .collect();
+        grid.push(row);
+    }
+    for p in path {
+        let cell = &mut grid[p.y][p.x];
+        *cell = if *cell == Cell::Tree { Cell::Crash } else { Cell::Clear };
+    }
+    grid
+}
+
+pub fn ascii(cells : &[Vec<Cell>], colour : bool) -> String {
+    let mut res = String::new();
+    for row in cells {
+        for c in row {
+            if colour {
+                res.push_str(c.ansi());
+            }
+            res.push(c.symbol());
+        }
+        if colour {
+            res.push_str("\x1b[0m");
+        }
+        res.push('\n');
+    }
+    res
+}
+
+// Binary PPM (P6), each cell drawn as a scale x scale block.
+pub fn write_ppm<W : Write>(cells : &[Vec<Cell>], scale : usize, mut out : W) -> io::Result<()> {
+    let scale = scale.max(1);
+    let width = cells.first().map_or(0, |r| r.len()) * scale;
+    let height = cells.len() * scale;
+    write!(out, "P6\n{} {}\n255\n", width, height)?;
+    for row in cells {
+        let mut line = Vec::with_capacity(width * 3);
+        for c in row {
+            for _ in 0..scale {
+                line.extend_from_slice(&c.rgb());
+            }
+        }
+        for _ in 0..scale {
+            out.write_all(&line)?;
+        }
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_map, walk};
+
+    #[test]
+    fn test_ascii() {
+        let m = parse_map("..#\n#..\n.#.\n".as_bytes()).unwrap();
+        let path : Vec<Point> = walk(&m, (0, 0), (2, 1)).collect();
+        let grid = cells(&m, &path);
+        assert_eq!(ascii(&grid, false), "O.#..#\n#.O#..\n.#..X.\n");
+    }
+
+    #[test]
+    fn test_ascii_colour() {
+        let m = parse_map("#\n".as_bytes()).unwrap();
+        let grid = cells(&m, &walk(&m, (0, 0), (1, 1)).collect::<Vec<_>>());
+        assert_eq!(ascii(&grid, true), "\x1b[1;31mX\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_ppm() {
+        let m = parse_map(".#\n".as_bytes()).unwrap();
+        let grid = cells(&m, &walk(&m, (0, 0), (1, 1)).collect::<Vec<_>>());
+        let mut out = Vec::new();
+        write_ppm(&grid, 2, &mut out).unwrap();
+        let header = b"P6\n4 2\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(out.len(), header.len() + 4 * 2 * 3);
+        assert_eq!(&out[header.len()..header.len() + 3], &Cell::Clear.rgb());
+        assert_eq!(&out[header.len() + 6..header.len() + 9], &Cell::Tree.rgb());
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt::Write;
+
+use crate::{walk_wrapped, Grid};
+
+pub struct Rider {
+    pub step : (usize, usize),
+    pub crashes : u64,
+    // Squares moved, counting both right and down. None if it does not fit
+    // in a u64.
+    pub distance : Option<u64>,
+}
+
+impl Rider {
+    pub fn new<G : Grid + ?Sized>(map : &G, start : (usize, usize), step : (usize, usize)) -> Rider {
+        let mut crashes = 0;
+        let mut visited : u64 = 0;
+        for p in walk_wrapped(map, start, step) {
+            crashes += map.has_tree(p.x, p.y) as u64;
+            visited += 1;
+        }
+        let moves = visited.saturating_sub(1);
+        let distance = (step.0 as u64).checked_add(step.1 as u64).and_then(|d| moves.checked_mul(d));
+        Rider { step, crashes, distance }
+    }
+}
+
+pub struct Report {
+    pub riders : Vec<Rider>,
+}
+
+impl Report {
+    pub fn new<G : Grid + ?Sized>(map : &G, start : (usize, usize), slopes : &[(usize, usize)]) -> Report {
+        Report { riders: slopes.iter().map(|&step| Rider::new(map, start, step)).collect() }
+    }
+
+    // None if the product does not fit in a u128.
+    pub fn product(&self) -> Option<u128> {
+        self.riders.iter().try_fold(1u128, |acc, r| acc.checked_mul(r.crashes as u128))
+    }
+
+    // None if any distance, or their sum, does not fit in a u64.
+    pub fn total_distance(&self) -> Option<u64> {
+        self.riders.iter().try_fold(0u64, |acc, r| acc.checked_add(r.distance?))
+    }
+
+    pub fn table(&self) -> String {
+        let mut res = String::new();
+        let shown = |n : Option<u64>| n.map_or("overflow".to_string(), |n| n.to_string());
+        writeln!(res, "{:>6} {:>6} {:>8} {:>9}", "right", "down", "crashes", "distance").unwrap();
+        for r in &self.riders {
+            writeln!(res, "{:>6} {:>6} {:>8} {:>9}", r.step.0, r.step.1, r.crashes, shown(r.distance)).unwrap();
+        }
+        match self.product() {
+            Some(p) => writeln!(res, "Product: {}", p).unwrap(),
+            None => writeln!(res, "Product: overflow").unwrap(),
+        }
+        writeln!(res, "Total distance: {}", shown(self.total_distance())).unwrap();
+        res
+    }
+
+    pub fn json(&self) -> String {
+        // Distances that overflow are null, like the product.
+        let shown = |n : Option<u64>| n.map_or("null".to_string(), |n| n.to_string());
+        let riders : Vec<String> = self.riders.iter()
+            .map(|r| format!("{{\"right\":{},\"down\":{},\"crashes\":{},\"distance\":{}}}",
+                             r.step.0, r.step.1, r.crashes, shown(r.distance)))
+            .collect();
+        // The product is a string since it may not fit in a JSON number.
+        let product = match self.product() {
+            Some(p) => format!("\"{}\"", p),
+            None => "null".to_string(),
+        };
+        format!("{{\"riders\":[{}],\"product\":{},\"total_distance\":{}}}",
+                riders.join(","), product, shown(self.total_distance()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_map;
+
+    fn example() -> Report {
+        let m = parse_map(crate::tests::EXAMPLE.as_bytes()).unwrap();
+        Report::new(&m, (0, 0), &[(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)])
+    }
+
+    #[test]
+    fn test_counts() {
+        let r = example();
+        let crashes : Vec<u64> = r.riders.iter().map(|r| r.crashes).collect();
+        assert_eq!(crashes, vec![2, 7, 3, 4, 2]);
+        assert_eq!(r.product(), Some(336));
+    }
+
+    #[test]
+    fn test_distance() {
+        let r = example();
+        assert_eq!(r.riders[0].distance, Some(20));
+        assert_eq!(r.riders[1].distance, Some(40));
+        assert_eq!(r.riders[4].distance, Some(15));
+        assert_eq!(r.total_distance(), Some(20 + 40 + 60 + 80 + 15));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let riders = (0..20).map(|_| Rider { step: (1, 1), crashes: u64::MAX, distance: Some(0) }).collect();
+        let r = Report { riders };
+        assert_eq!(r.product(), None);
+        assert!(r.table().contains("Product: overflow"));
+        assert!(r.json().contains("\"product\":null"));
+    }
+
+    #[test]
+    fn test_distance_overflow() {
+        let m = parse_map("#.\n.#\n".as_bytes()).unwrap();
+        let r = Report::new(&m, (0, 0), &[(usize::MAX, 1)]);
+        assert_eq!(r.riders[0].distance, None);
+        assert!(r.table().contains("overflow"));
+        let riders = vec![
+            Rider { step: (1, 1), crashes: 0, distance: Some(u64::MAX) },
+            Rider { step: (1, 1), crashes: 0, distance: Some(1) },
+        ];
+        let r = Report { riders };
+        assert_eq!(r.total_distance(), None);
+        assert!(r.table().contains("Total distance: overflow"));
+        assert!(r.json().ends_with("\"total_distance\":null}"));
+        let r = Report { riders: vec![Rider { step: (1, 1), crashes: 0, distance: None }] };
+        assert_eq!(r.total_distance(), None);
+        assert!(r.json().contains("\"distance\":null"));
+    }
+
+    #[test]
+    fn test_huge_step() {
+        let m = parse_map("..#\n#..\n...\n.#.\n".as_bytes()).unwrap();
+        let r = Report::new(&m, (0, 0), &[(usize::MAX / 2, 1), (usize::MAX, 1), (3, 1)]);
+        let crashes : Vec<u64> = r.riders.iter().map(|r| r.crashes).collect();
+        assert_eq!(crashes, vec![0, 1, 1]);
+        assert_eq!(r.riders[0].distance, None);
+        assert_eq!(r.riders[2].distance, Some(12));
+    }
+
+    #[test]
+    fn test_json() {
+        let m = parse_map("#.\n.#\n".as_bytes()).unwrap();
+        let r = Report::new(&m, (0, 0), &[(1, 1)]);
+        assert_eq!(r.json(), "{\"riders\":[{\"right\":1,\"down\":1,\"crashes\":2,\"distance\":2}],\"product\":\"2\",\"total_distance\":2}");
+    }
+
+    #[test]
+    fn test_table() {
+        let m = parse_map("#.\n.#\n".as_bytes()).unwrap();
+        let r = Report::new(&m, (0, 0), &[(1, 1)]);
+        let t = r.table();
+        let lines : Vec<&str> = t.lines().collect();
+        assert_eq!(lines[1], "     1      1        2         2");
+        assert_eq!(lines[2], "Product: 2");
+        assert_eq!(lines[3], "Total distance: 2");
+    }
+}
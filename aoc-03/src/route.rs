@@ -0,0 +1,147 @@
+use std::ops::RangeInclusive;
+
+use crate::{Map, Point};
+
+pub struct Route {
+    // Unwrapped positions, like walk() returns.
+    pub path : Vec<Point>,
+    pub cost : usize,
+}
+
+pub fn slopes(right : RangeInclusive<usize>, down : usize) -> Vec<(usize, usize)> {
+    right.map(|r| (r, down)).collect()
+}
+
+// Finds the route from start to the bottom that hits the fewest trees, where
+// each step is one of the given (right, down) moves. Since every move goes
+// down, the cells can be relaxed row by row. The horizontal position only
+// matters modulo the map width, same as in has_tree(). A route ends once no
+// move stays inside the map.
+//
+// Returns None if there are no moves, a move does not go down, or the start
+// is below the map.
+pub fn least_crashes(map : &Map, start : (usize, usize), moves : &[(usize, usize)]) -> Option<Route> {
+    let min_down = moves.iter().map(|m| m.1).min()?;
+    if min_down == 0 || start.1 >= map.height {
+        return None;
+    }
+
+    let w = map.width;
+    let h = map.height;
+    let idx = |x : usize, y : usize| y * w + x;
+    let hit = |x : usize, y : usize| map.has_tree(x, y) as usize;
+
+    let mut cost = vec![usize::MAX; w * h];
+    // For each cell, the cell it was reached from and the move used.
+    let mut prev : Vec<Option<(usize, usize)>> = vec![None; w * h];
+    let mut best : Option<(usize, usize)> = None;
+
+    let sx = start.0 % w;
+    cost[idx(sx, start.1)] = hit(sx, start.1);
+
+    for y in start.1..h {
+        for x in 0..w {
+            let c = cost[idx(x, y)];
+            if c == usize::MAX {
+                continue;
+            }
+            if y + min_down >= h {
+                if best.map_or(true, |(bc, _)| c < bc) {
+                    best = Some((c, idx(x, y)));
+                }
+                continue;
+            }
+            for (i, &(dx, dy)) in moves.iter().enumerate() {
+                let ny = y + dy;
+                if ny >= h {
+                    continue;
+                }
+                let nx = (x + dx) % w;
+                let nc = c + hit(nx, ny);
+                if nc < cost[idx(nx, ny)] {
+                    cost[idx(nx, ny)] = nc;
+                    prev[idx(nx, ny)] = Some((idx(x, y), i));
+                }
+            }
+        }
+    }
+
+    let (cost, end) = best?;
+    let mut steps = Vec::new();
+    let mut at = end;
+    while let Some((from, m)) = prev[at] {
+        steps.push(moves[m]);
+        at = from;
+    }
+
+    let mut p = Point { x: start.0, y: start.1 };
+    let mut path = vec![Point { x: p.x, y: p.y }];
+    for step in steps.into_iter().rev() {
+        p = p + Point { x: step.0, y: step.1 };
+        path.push(Point { x: p.x, y: p.y });
+    }
+
+    Some(Route { path, cost })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_crashes, parse_map};
+
+    fn check_route(map : &Map, route : &Route, moves : &[(usize, usize)]) {
+        let hits = route.path.iter().filter(|p| map.has_tree(p.x, p.y)).count();
+        assert_eq!(hits, route.cost);
+        for pair in route.path.windows(2) {
+            let step = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+            assert!(moves.contains(&step));
+        }
+    }
+
+    #[test]
+    fn test_example() {
+        let m = parse_map(crate::tests::EXAMPLE.as_bytes()).unwrap();
+        let moves = slopes(1..=3, 1);
+        let route = least_crashes(&m, (0, 0), &moves).unwrap();
+        check_route(&m, &route, &moves);
+        assert_eq!(route.path.len(), 11);
+        assert_eq!(route.path.last().unwrap().y, 10);
+        for r in 1..=3 {
+            assert!(route.cost as i64 <= find_crashes(&m, (0, 0), (r, 1)));
+        }
+    }
+
+    #[test]
+    fn test_avoids_trees() {
+        let m = parse_map("...\n##.\n#.#\n".as_bytes()).unwrap();
+        let moves = slopes(1..=2, 1);
+        let route = least_crashes(&m, (0, 0), &moves).unwrap();
+        check_route(&m, &route, &moves);
+        assert_eq!(route.cost, 0);
+        assert!(route.path[1] == Point { x: 2, y: 1 });
+        assert!(route.path[2] == Point { x: 4, y: 2 });
+    }
+
+    #[test]
+    fn test_unavoidable() {
+        let m = parse_map("#\n#\n.\n".as_bytes()).unwrap();
+        let route = least_crashes(&m, (0, 0), &slopes(1..=3, 1)).unwrap();
+        assert_eq!(route.cost, 2);
+    }
+
+    #[test]
+    fn test_stops_when_moves_leave_map() {
+        let m = parse_map("..\n..\n..\n..\n".as_bytes()).unwrap();
+        let route = least_crashes(&m, (0, 0), &[(1, 3)]).unwrap();
+        assert_eq!(route.path.len(), 2);
+        assert!(route.path[1] == Point { x: 1, y: 3 });
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let m = parse_map("..\n..\n".as_bytes()).unwrap();
+        assert!(least_crashes(&m, (0, 0), &[]).is_none());
+        assert!(least_crashes(&m, (0, 0), &[(1, 0)]).is_none());
+        assert!(least_crashes(&m, (0, 5), &[(1, 1)]).is_none());
+    }
+}
@@ -0,0 +1,239 @@
+use std::io;
+use std::io::prelude::*;
+use std::time::Duration;
+
+use crossterm::cursor;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use crate::report::Rider;
+use crate::{walk, Map, Point};
+
+const MIN_DELAY : u64 = 10;
+const MAX_DELAY : u64 = 2000;
+
+// Each rider's whole path is worked out up front, and the animation shows
+// more of it on every tick.
+pub struct Animation {
+    pub riders : Vec<Rider>,
+    paths : Vec<Vec<Point>>,
+    // Positions shown so far on each path.
+    shown : usize,
+}
+
+impl Animation {
+    pub fn new(map : &Map, start : (usize, usize), slopes : &[(usize, usize)]) -> Animation {
+        let riders = slopes.iter().map(|&step| Rider::new(map, start, step)).collect();
+        let paths = slopes.iter().map(|&step| walk(map, start, step).collect()).collect();
+        Animation { riders, paths, shown: 1 }
+    }
+
+    // Where rider i has been so far, the last point being where it is now.
+    pub fn trail(&self, i : usize) -> &[Point] {
+        let path = &self.paths[i];
+        &path[..self.shown.min(path.len())]
+    }
+
+    pub fn crashes(&self, map : &Map, i : usize) -> usize {
+        self.trail(i).iter().filter(|p| map.has_tree(p.x, p.y)).count()
+    }
+
+    // Moves every rider that has not reached the bottom one step. Returns
+    // false once nobody could move.
+    pub fn tick(&mut self) -> bool {
+        if self.finished() {
+            return false;
+        }
+        self.shown += 1;
+        true
+    }
+
+    pub fn finished(&self) -> bool {
+        self.paths.iter().all(|p| p.len() <= self.shown)
+    }
+
+    // Map rows top..top+rows, cut to cols characters. Since the map repeats,
+    // riders are drawn at their position modulo the map width. Trails use the
+    // same symbols as render::ascii(), the riders themselves are numbered.
+    pub fn frame(&self, map : &Map, top : usize, rows : usize, cols : usize) -> Vec<String> {
+        let bottom = map.height.min(top + rows);
+        let width = map.width.min(cols);
+        let mut grid : Vec<Vec<char>> = (top..bottom)
+            .map(|y| (0..width).map(|x| if map.has_tree(x, y) { '#' } else { '.' }).collect())
+            .collect();
+
+        let mut put = |p : &Point, c : char| {
+            let x = p.x % map.width;
+            if p.y >= top && p.y < bottom && x < width {
+                grid[p.y - top][x] = c;
+            }
+        };
+        for i in 0..self.riders.len() {
+            for p in self.trail(i) {
+                put(p, if map.has_tree(p.x, p.y) { 'X' } else { 'O' });
+            }
+        }
+        for i in 0..self.riders.len() {
+            if let Some(p) = self.trail(i).last() {
+                put(p, std::char::from_digit((i % 9 + 1) as u32, 10).unwrap());
+            }
+        }
+
+        grid.into_iter().map(|row| row.into_iter().collect()).collect()
+    }
+
+    pub fn status(&self, map : &Map) -> Vec<String> {
+        self.riders.iter().enumerate()
+            .map(|(i, r)| format!("{}: right {}, down {}  crashes {} of {}",
+                                  i % 9 + 1, r.step.0, r.step.1, self.crashes(map, i), r.crashes))
+            .collect()
+    }
+}
+
+// Keeps the lowest rider still moving in the middle of the screen.
+fn scroll(anim : &Animation, map : &Map, rows : usize) -> usize {
+    let y = (0..anim.riders.len())
+        .filter(|&i| anim.trail(i).len() < anim.paths[i].len())
+        .filter_map(|i| anim.trail(i).last().map(|p| p.y))
+        .max()
+        .unwrap_or(map.height);
+    y.saturating_sub(rows / 2).min(map.height.saturating_sub(rows))
+}
+
+fn draw<W : Write>(out : &mut W, anim : &Animation, map : &Map, paused : bool, delay : u64) -> io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    let status = anim.status(map);
+    let rows = (rows as usize).saturating_sub(status.len() + 2).max(1);
+    let top = scroll(anim, map, rows);
+
+    queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+    for line in anim.frame(map, top, rows, cols as usize) {
+        write!(out, "{}\r\n", line)?;
+    }
+    write!(out, "\r\n")?;
+    for line in status {
+        write!(out, "{}\r\n", line)?;
+    }
+    let state = if anim.finished() { "done" } else if paused { "paused" } else { "running" };
+    write!(out, "[{}, {} ms] space: pause  n: step  +/-: speed  r: restart  q: quit", state, delay)?;
+    out.flush()
+}
+
+fn event_loop<W : Write>(out : &mut W, map : &Map, slopes : &[(usize, usize)]) -> io::Result<()> {
+    let mut anim = Animation::new(map, (0, 0), slopes);
+    let mut paused = false;
+    let mut delay = 200;
+
+    loop {
+        draw(out, &anim, map, paused, delay)?;
+        let wait = if paused || anim.finished() { MAX_DELAY } else { delay };
+        if !event::poll(Duration::from_millis(wait))? {
+            if !paused {
+                anim.tick();
+            }
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char(' ') => paused = !paused,
+                KeyCode::Char('n') | KeyCode::Right => {
+                    paused = true;
+                    anim.tick();
+                }
+                KeyCode::Char('+') => delay = (delay / 2).max(MIN_DELAY),
+                KeyCode::Char('-') => delay = (delay * 2).min(MAX_DELAY),
+                KeyCode::Char('r') => anim = Animation::new(map, (0, 0), slopes),
+                _ => {}
+            }
+        }
+    }
+}
+
+// Raw mode and the alternate screen, undone when dropped so that the
+// terminal is restored however run() returns.
+struct Screen;
+
+impl Screen {
+    fn enter<W : Write>(out : &mut W) -> io::Result<Screen> {
+        terminal::enable_raw_mode()?;
+        let screen = Screen;
+        execute!(out, EnterAlternateScreen, cursor::Hide)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// Keys are read from the terminal, so the map can still be piped in on stdin.
+pub fn run(map : &Map, slopes : &[(usize, usize)]) -> io::Result<()> {
+    let mut out = io::stdout();
+    let _screen = Screen::enter(&mut out)?;
+    event_loop(&mut out, map, slopes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_crashes, parse_map};
+
+    #[test]
+    fn test_counts_match_find_crashes() {
+        let m = parse_map(crate::tests::EXAMPLE.as_bytes()).unwrap();
+        let slopes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+        let mut anim = Animation::new(&m, (0, 0), &slopes);
+        let mut ticks = 0;
+        while anim.tick() {
+            ticks += 1;
+        }
+        assert_eq!(ticks, 10);
+        assert!(anim.finished());
+        for (i, &step) in slopes.iter().enumerate() {
+            assert_eq!(anim.crashes(&m, i) as i64, find_crashes(&m, (0, 0), step));
+            assert_eq!(anim.riders[i].crashes as i64, find_crashes(&m, (0, 0), step));
+        }
+        assert_eq!(anim.trail(4).len(), 6);
+    }
+
+    #[test]
+    fn test_frame() {
+        let m = parse_map("...\n.#.\n..#\n...\n".as_bytes()).unwrap();
+        let mut anim = Animation::new(&m, (0, 0), &[(1, 1), (2, 1)]);
+        anim.tick();
+        anim.tick();
+        assert_eq!(anim.frame(&m, 0, 4, 80), vec!["O..", ".XO", ".21", "..."]);
+        assert_eq!(anim.frame(&m, 1, 2, 2), vec![".X", ".2"]);
+        assert_eq!(anim.status(&m)[0], "1: right 1, down 1  crashes 2 of 2");
+        assert_eq!(anim.status(&m)[1], "2: right 2, down 1  crashes 0 of 0");
+    }
+
+    #[test]
+    fn test_trail() {
+        let m = parse_map(".#.\n...\n...\n".as_bytes()).unwrap();
+        let mut anim = Animation::new(&m, (1, 0), &[(1, 1)]);
+        anim.tick();
+        assert_eq!(anim.frame(&m, 0, 3, 80), vec![".X.", "..1", "..."]);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let m = parse_map("...\n".repeat(20).as_bytes()).unwrap();
+        let mut anim = Animation::new(&m, (0, 0), &[(1, 1)]);
+        assert_eq!(scroll(&anim, &m, 10), 0);
+        for _ in 0..12 {
+            anim.tick();
+        }
+        assert_eq!(scroll(&anim, &m, 10), 7);
+        while anim.tick() {}
+        assert_eq!(scroll(&anim, &m, 10), 10);
+    }
+}
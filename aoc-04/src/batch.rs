@@ -0,0 +1,104 @@
+use std::io;
+use std::io::prelude::*;
+
+// The lines of one passport, and the 1-based line it started on.
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    pub line : usize,
+    pub lines : Vec<String>,
+}
+
+// Splits input into records separated by one or more blank lines. Lines that
+// only hold whitespace count as blank, and trailing whitespace (including the
+// '\r' of Windows line endings) is dropped.
+pub struct Records<R> {
+    reader : R,
+    line : usize,
+}
+
+pub fn records<R : BufRead>(reader : R) -> Records<R> {
+    Records { reader, line: 0 }
+}
+
+impl<R : BufRead> Iterator for Records<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<io::Result<Record>> {
+        let mut record = Record { line: 0, lines: Vec::new() };
+        let mut buf = String::new();
+        loop {
+            buf.clear();
+            match self.reader.read_line(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            self.line += 1;
+
+            let l = buf.trim_end();
+            if l.is_empty() {
+                if record.lines.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            if record.lines.is_empty() {
+                record.line = self.line;
+            }
+            record.lines.push(l.to_string());
+        }
+
+        if record.lines.is_empty() {
+            None
+        } else {
+            Some(Ok(record))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input : &str) -> Vec<Record> {
+        records(input.as_bytes()).map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_records() {
+        let r = read("a:1 b:2\nc:3\n\nd:4\n");
+        assert_eq!(r, vec![
+            Record { line: 1, lines: vec!["a:1 b:2".to_string(), "c:3".to_string()] },
+            Record { line: 4, lines: vec!["d:4".to_string()] },
+        ]);
+    }
+
+    #[test]
+    fn test_ends_at_eof() {
+        assert!(read("").is_empty());
+        assert!(read("\n\n  \n").is_empty());
+        assert_eq!(read("a:1").len(), 1);
+        let mut it = records("a:1\n".as_bytes());
+        assert!(it.next().is_some());
+        assert!(it.next().is_none());
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn test_blank_and_whitespace_lines() {
+        let r = read("\n\na:1 \n \t\n\n\nb:2\t\n   \n");
+        assert_eq!(r, vec![
+            Record { line: 3, lines: vec!["a:1".to_string()] },
+            Record { line: 7, lines: vec!["b:2".to_string()] },
+        ]);
+    }
+
+    #[test]
+    fn test_crlf() {
+        let r = read("a:1\r\nb:2\r\n\r\nc:3\r\n");
+        assert_eq!(r, vec![
+            Record { line: 1, lines: vec!["a:1".to_string(), "b:2".to_string()] },
+            Record { line: 4, lines: vec!["c:3".to_string()] },
+        ]);
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+#[cfg(feature = "bench")]
+use std::time::Instant;
+
+use regex::Regex;
+
+use crate::batch::Record;
+use crate::engine;
+use crate::repair::Mode;
+use crate::profile::Profiles;
+use crate::strict::Strictness;
+
+// The puzzle examples, valid and invalid ones mixed.
+const SAMPLES : [&str; 12] = [
+    "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 cid:147 hgt:183cm",
+    "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884 hcl:#cfa07d byr:1929",
+    "hcl:#ae17e1 iyr:2013 eyr:2024 ecl:brn pid:760753108 byr:1931 hgt:179cm",
+    "hcl:#cfa07d eyr:2025 pid:166559648 iyr:2011 ecl:brn hgt:59in",
+    "eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926",
+    "iyr:2019 hcl:#602927 eyr:1967 hgt:170cm ecl:grn pid:012533040 byr:1946",
+    "hcl:dab227 iyr:2012 ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277",
+    "hgt:59cm ecl:zzz eyr:2038 hcl:74454a iyr:2023 pid:3556412378 byr:2007",
+    "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f",
+    "eyr:2029 ecl:blu cid:129 byr:1989 iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm",
+    "hcl:#888785 hgt:164cm byr:2001 iyr:2015 cid:88 pid:545766238 ecl:hzl eyr:2022",
+    "iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719",
+];
+
+pub fn synthetic(n : usize) -> Vec<Record> {
+    (0..n).map(|i| Record { line: i * 2 + 1, lines: vec![SAMPLES[i % SAMPLES.len()].to_string()] }).collect()
+}
+
+// How validation worked before the schema: a fixed field list, and every
+// pattern compiled again for every value, with the same patterns. Kept only
+// as a baseline.
+#[allow(clippy::regex_creation_in_loops)]
+fn legacy_valid(fields : &HashMap<String, String>) -> bool {
+    let necessary = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
+    if !necessary.iter().all(|f| fields.contains_key(*f)) {
+        return false;
+    }
+    for (k, v) in fields {
+        let ok = match k.as_str() {
+            "byr" => v.parse::<i32>().is_ok_and(|n| (1920..=2002).contains(&n)),
+            "iyr" => v.parse::<i32>().is_ok_and(|n| (2010..=2020).contains(&n)),
+            "eyr" => v.parse::<i32>().is_ok_and(|n| (2020..=2030).contains(&n)),
+            "hgt" => {
+                let r_cm = Regex::new(r"^(?P<height>[0-9]{3})cm").unwrap();
+                let r_in = Regex::new(r"^(?P<height>[0-9]{2})in").unwrap();
+                if let Some(m) = r_cm.captures(v) {
+                    (150..=193).contains(&m["height"].parse::<i32>().unwrap())
+                } else if let Some(m) = r_in.captures(v) {
+                    (59..=76).contains(&m["height"].parse::<i32>().unwrap())
+                } else {
+                    false
+                }
+            }
+            "hcl" => Regex::new(r"#[0-9a-f]{6}$").unwrap().is_match(v),
+            "ecl" => ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"].contains(&v.as_str()),
+            "pid" => Regex::new(r"^[0-9]{9}$").unwrap().is_match(v),
+            _ => true,
+        };
+        if !ok {
+            return false;
+        }
+    }
+    true
+}
+
+fn legacy_fields(record : &Record) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    for l in &record.lines {
+        for entry in l.split(' ') {
+            let e : Vec<&str> = entry.split(':').collect();
+            fields.insert(e[0].to_string(), e[1].to_string());
+        }
+    }
+    fields
+}
+
+#[cfg(feature = "bench")]
+fn timed(name : &str, n : usize, f : impl FnOnce() -> usize) -> f64 {
+    let start = Instant::now();
+    let valid = f();
+    let secs = start.elapsed().as_secs_f64();
+    let rate = n as f64 / secs;
+    println!("{:<24} {:>8.3} s {:>14.0} records/s  ({} valid)", name, secs, rate, valid);
+    rate
+}
+
+// Validates n generated passports the old way, with the compiled profiles on
+// one thread, and with the compiled profiles on every thread.
+#[cfg(feature = "bench")]
+pub fn run(n : usize, threads : usize) {
+    let records = synthetic(n);
+    let profiles = Profiles::builtin();
+
+    let legacy = timed("legacy", n, || {
+        records.iter().filter(|r| legacy_valid(&legacy_fields(r))).count()
+    });
+    let serial = timed("compiled, 1 thread", n, || {
+        engine::check_all(profiles, Strictness::default(), Mode::Suggest, &records, 1).iter().filter(|c| c.part2).count()
+    });
+    let parallel = timed(&format!("compiled, {} threads", threads), n, || {
+        engine::check_all(profiles, Strictness::default(), Mode::Suggest, &records, threads).iter().filter(|c| c.part2).count()
+    });
+    println!("Speedup: {:.1}x on 1 thread, {:.1}x on {} threads", serial / legacy, parallel / legacy, threads);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_agrees_on_samples() {
+        let profiles = Profiles::builtin();
+        for r in synthetic(SAMPLES.len()) {
+            let checked = engine::check(profiles, Strictness::default(), Mode::Suggest, &r);
+            assert_eq!(legacy_valid(&legacy_fields(&r)), checked.part2, "{}", r.lines[0]);
+        }
+    }
+}
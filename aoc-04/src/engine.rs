@@ -0,0 +1,144 @@
+use std::thread;
+
+use crate::batch::Record;
+use crate::repair::{Fix, Mode};
+use crate::profile::{Profile, Profiles};
+use crate::report::Report;
+use crate::strict::Strictness;
+use crate::Passport;
+
+pub struct Checked<'a> {
+    // The profile that matched, or the closest one if none did.
+    pub profile : &'a Profile,
+    pub passport : Passport<'a>,
+    pub part1 : bool,
+    // Per-passport rules only, batch rules need every passport in order.
+    pub part2 : bool,
+    // What is wrong with the passport under its profile, if anything.
+    pub report : Report,
+    // Applied already with Mode::Apply, otherwise only suggested for
+    // passports that fail part 2.
+    pub fixes : Vec<Fix>,
+}
+
+fn check_profile<'a>(profile : &'a Profile, strictness : Strictness, mode : Mode, record : &Record) -> Checked<'a> {
+    let mut p = Passport::with_schema(&profile.schema);
+    p.set_strictness(strictness);
+    p.set_first_line(record.line);
+    for l in &record.lines {
+        p.parse_line(l);
+    }
+    let mut fixes = if mode == Mode::Apply { p.apply_fixes() } else { Vec::new() };
+    let part1 = p.is_valid();
+    let part2 = p.is_valid_pt2();
+    let report = p.report();
+    if mode == Mode::Suggest && !part2 {
+        fixes = p.fixes();
+    }
+    Checked { profile, passport: p, part1, part2, report, fixes }
+}
+
+// Tries the profiles in order and keeps the first one the record satisfies.
+// If there is none, keeps the one with the fewest problems, the first of
+// them on a tie. Part 1 holds if the record has the fields of any profile.
+pub fn check<'a>(profiles : &'a Profiles, strictness : Strictness, mode : Mode, record : &Record) -> Checked<'a> {
+    let mut closest : Option<(usize, Checked)> = None;
+    let mut part1 = false;
+    for profile in &profiles.profiles {
+        let c = check_profile(profile, strictness, mode, record);
+        if c.part2 {
+            return c;
+        }
+        part1 |= c.part1;
+        let problems = c.report.problems.len();
+        let better = match &closest {
+            Some((n, _)) => problems < *n,
+            None => true,
+        };
+        if better {
+            closest = Some((problems, c));
+        }
+    }
+    let (_, mut c) = closest.expect("no profiles");
+    c.part1 = part1;
+    c
+}
+
+// Splits the records into one chunk per thread, all sharing the profiles and
+// their compiled patterns. The results are in input order.
+pub fn check_all<'a>(profiles : &'a Profiles, strictness : Strictness, mode : Mode, records : &[Record], threads : usize)
+        -> Vec<Checked<'a>> {
+    if threads <= 1 || records.len() < 2 {
+        return records.iter().map(|r| check(profiles, strictness, mode, r)).collect();
+    }
+    let chunk = records.len().div_ceil(threads);
+    thread::scope(|s| {
+        let handles : Vec<_> = records.chunks(chunk)
+            .map(|c| s.spawn(move || c.iter().map(|r| check(profiles, strictness, mode, r)).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench;
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        let profiles = Profiles::builtin();
+        let records = bench::synthetic(1000);
+        let serial = check_all(profiles, Strictness::default(), Mode::Suggest, &records, 1);
+        let parallel = check_all(profiles, Strictness::default(), Mode::Suggest, &records, 7);
+        assert_eq!(serial.len(), 1000);
+        assert_eq!(parallel.len(), 1000);
+        for (a, b) in serial.iter().zip(parallel.iter()) {
+            assert_eq!(a.part1, b.part1);
+            assert_eq!(a.part2, b.part2);
+            assert_eq!(a.passport.fields.to_string(), b.passport.fields.to_string());
+        }
+        assert!(serial.iter().any(|c| c.part2));
+        assert!(serial.iter().any(|c| c.part1 && !c.part2));
+    }
+
+    #[test]
+    fn test_fewer_records_than_threads() {
+        let records = bench::synthetic(3);
+        assert_eq!(check_all(Profiles::builtin(), Strictness::default(), Mode::Suggest, &records, 8).len(), 3);
+        assert!(check_all(Profiles::builtin(), Strictness::default(), Mode::Suggest, &[], 8).is_empty());
+    }
+
+    fn record(line : &str) -> Record {
+        Record { line: 1, lines: vec![line.to_string()] }
+    }
+
+    fn classify(line : &str) -> (String, bool, bool) {
+        let c = check(Profiles::builtin(), Strictness::default(), Mode::Suggest, &record(line));
+        (c.profile.name.clone(), c.part1, c.part2)
+    }
+
+    #[test]
+    fn test_classify() {
+        let npc = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f";
+        assert_eq!(classify(npc), ("north-pole-credential".to_string(), true, true));
+        assert_eq!(classify(&format!("{} cid:1", npc)), ("passport".to_string(), true, true));
+        let visa = "vid:V12345678 vty:work iss:NPL byr:1980 iyr:2018 eyr:2022";
+        assert_eq!(classify(visa), ("visa".to_string(), true, true));
+        assert_eq!(classify(&format!("{} pid:087499704", visa)), ("visa".to_string(), true, true));
+        assert_eq!(classify("crw:000042 rol:pilot byr:1975 hgt:180cm"), ("crew-id".to_string(), true, true));
+    }
+
+    #[test]
+    fn test_closest_profile() {
+        // A visa valid for too long is still closest to a visa.
+        assert_eq!(classify("vid:V12345678 vty:work iss:NPL byr:1980 iyr:2015 eyr:2021"), ("visa".to_string(), true, false));
+        // A North Pole Credential with a bad height.
+        let bad = "pid:087499704 hgt:74 ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f";
+        assert_eq!(classify(bad), ("north-pole-credential".to_string(), true, false));
+        // Part 1 only needs the fields of some profile.
+        assert_eq!(classify("crw:x rol:x byr:x"), ("crew-id".to_string(), true, false));
+        assert!(!classify("byr:1980").1);
+    }
+}
+
@@ -0,0 +1,311 @@
+use std::io;
+use std::io::prelude::*;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::batch::{self, Record};
+use crate::report::Problem;
+use crate::typed::TypedPassport;
+
+// Extra columns are prefixed so they never clash with a schema field, and
+// readers skip anything starting with the prefix, apart from the fields CSV
+// has no column for.
+const META_PREFIX : char = '_';
+const EXTRA_COLUMN : &str = "_extra";
+const LINE_COLUMN : &str = "_line";
+const REASONS_COLUMN : &str = "_reasons";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    // The puzzle's own key:value format, blank lines between passports.
+    Passport,
+    // One JSON object per line.
+    Jsonl,
+    // A header row with the columns, then one row per passport.
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Format, String> {
+        match s {
+            "passport" => Ok(Format::Passport),
+            "jsonl" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format {:?}, expected passport, jsonl or csv", s)),
+        }
+    }
+}
+
+fn invalid_data(line : usize, msg : impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+}
+
+// Turns key/value pairs back into the line parse_line() reads. Empty values
+// mean the field was missing, and the extra column already holds tokens.
+fn to_record(line : usize, pairs : Vec<(String, String)>) -> Record {
+    let tokens : Vec<String> = pairs.into_iter()
+        .filter(|(k, v)| (k == EXTRA_COLUMN || !k.starts_with(META_PREFIX)) && !v.is_empty())
+        .map(|(k, v)| if k == EXTRA_COLUMN { v } else { format!("{}:{}", k, v) })
+        .collect();
+    Record { line, lines: vec![tokens.join(" ")] }
+}
+
+fn json_record(line : usize, text : &str) -> io::Result<Record> {
+    let obj : Map<String, Value> = serde_json::from_str(text).map_err(|e| invalid_data(line, e))?;
+    let mut pairs = Vec::new();
+    for (k, v) in obj {
+        match v {
+            Value::String(s) => pairs.push((k, s)),
+            _ if k.starts_with(META_PREFIX) => {}
+            _ => return Err(invalid_data(line, format!("{} is not a string", k))),
+        }
+    }
+    Ok(to_record(line, pairs))
+}
+
+fn csv_split(line : usize, text : &str) -> io::Result<Vec<String>> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if cell.is_empty() => quoted = true,
+            (',', false) => cells.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+    if quoted {
+        return Err(invalid_data(line, "unterminated quote"));
+    }
+    cells.push(cell);
+    Ok(cells)
+}
+
+fn csv_quote(s : &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// Reads records in any format. Rows are single lines, so CSV cells cannot
+// hold line breaks.
+pub fn read<'r, R : BufRead + 'r>(reader : R, format : Format) -> Box<dyn Iterator<Item = io::Result<Record>> + 'r> {
+    if format == Format::Passport {
+        return Box::new(batch::records(reader));
+    }
+    let lines = reader.lines().enumerate().map(|(i, l)| (i + 1, l));
+    match format {
+        Format::Passport => unreachable!(),
+        Format::Jsonl => Box::new(lines.filter_map(|(i, l)| match l {
+            Ok(l) if l.trim().is_empty() => None,
+            Ok(l) => Some(json_record(i, &l)),
+            Err(e) => Some(Err(e)),
+        })),
+        Format::Csv => {
+            let mut header : Option<Vec<String>> = None;
+            Box::new(lines.filter_map(move |(i, l)| {
+                let l = match l {
+                    Ok(l) => l,
+                    Err(e) => return Some(Err(e)),
+                };
+                let l = l.trim_end_matches('\r');
+                if l.is_empty() {
+                    return None;
+                }
+                let cells = match csv_split(i, l) {
+                    Ok(c) => c,
+                    Err(e) => return Some(Err(e)),
+                };
+                let columns = match &header {
+                    Some(h) => h,
+                    None => {
+                        header = Some(cells);
+                        return None;
+                    }
+                };
+                if cells.len() != columns.len() {
+                    let msg = format!("{} cells, but the header has {}", cells.len(), columns.len());
+                    return Some(Err(invalid_data(i, msg)));
+                }
+                Some(Ok(to_record(i, columns.iter().cloned().zip(cells).collect())))
+            }))
+        }
+    }
+}
+
+// Writes passports with one column per field in columns, in that order,
+// each value that parses in its canonical form: integers without signs or
+// leading zeroes, heights in their shortest form and so on. Fields the
+// schema does not know follow as they were, in CSV as key:value tokens in
+// one extra column. Rejected passports also get the line they started on
+// and the reasons they were rejected.
+pub struct Writer<'a, W : Write> {
+    out : W,
+    format : Format,
+    columns : Vec<&'a str>,
+    with_reasons : bool,
+}
+
+impl<'a, W : Write> Writer<'a, W> {
+    pub fn new(mut out : W, format : Format, columns : Vec<&'a str>, with_reasons : bool) -> io::Result<Writer<'a, W>> {
+        if format == Format::Csv {
+            let mut header : Vec<&str> = columns.clone();
+            header.push(EXTRA_COLUMN);
+            if with_reasons {
+                header.push(LINE_COLUMN);
+                header.push(REASONS_COLUMN);
+            }
+            writeln!(out, "{}", header.join(","))?;
+        }
+        Ok(Writer { out, format, columns, with_reasons })
+    }
+
+    pub fn write(&mut self, line : usize, passport : &TypedPassport, reasons : &[Problem]) -> io::Result<()> {
+        let reasons : Vec<String> = reasons.iter().map(|r| r.to_string()).collect();
+        let unknown = passport.unknown();
+        match self.format {
+            Format::Passport => {
+                let tokens : Vec<String> = self.columns.iter()
+                    .filter_map(|c| passport.canonical(c).map(|v| format!("{}:{}", c, v)))
+                    .chain(unknown.iter().map(|(k, v)| format!("{}:{}", k, v)))
+                    .collect();
+                write!(self.out, "{}\n\n", tokens.join(" "))
+            }
+            Format::Jsonl => {
+                let mut obj = Map::new();
+                for c in &self.columns {
+                    if let Some(v) = passport.canonical(c) {
+                        obj.insert(c.to_string(), Value::String(v));
+                    }
+                }
+                for (k, v) in unknown {
+                    obj.insert(k.to_string(), Value::String(v.to_string()));
+                }
+                if self.with_reasons {
+                    obj.insert(LINE_COLUMN.to_string(), Value::from(line));
+                    obj.insert(REASONS_COLUMN.to_string(), Value::from(reasons));
+                }
+                writeln!(self.out, "{}", Value::Object(obj))
+            }
+            Format::Csv => {
+                let mut cells : Vec<String> = self.columns.iter()
+                    .map(|c| passport.canonical(c).map_or(String::new(), |v| csv_quote(&v)))
+                    .collect();
+                let extra : Vec<String> = unknown.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+                cells.push(csv_quote(&extra.join(" ")));
+                if self.with_reasons {
+                    cells.push(line.to_string());
+                    cells.push(csv_quote(&reasons.join("; ")));
+                }
+                writeln!(self.out, "{}", cells.join(","))
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+    use crate::token::{ErrorKind, TokenError};
+
+    fn fields(line : &str) -> TypedPassport<'static> {
+        let mut p = TypedPassport::new(Schema::default_schema());
+        for e in line.split(' ') {
+            let (k, v) = e.split_once(':').unwrap();
+            p.insert(k, v);
+        }
+        p
+    }
+
+    fn records(text : &str, format : Format) -> Vec<Record> {
+        read(text.as_bytes(), format).map(|r| r.unwrap()).collect()
+    }
+
+    // Valid, but not in canonical form.
+    const PASSPORT : &str = "pid:087499704 hgt:074in ecl:grn iyr:+2012 eyr:02030 byr:1980 hcl:#623a2f";
+    const NORMALISED : &str = "byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn pid:087499704";
+
+    fn export(format : Format, with_reasons : bool) -> String {
+        let mut out = Vec::new();
+        let mut w = Writer::new(&mut out, format, Schema::default_schema().columns(), with_reasons).unwrap();
+        let reasons = vec![Problem::Missing { field: "cid".to_string() }, Problem::Malformed { error: TokenError { token: "a,\"b\"".to_string(), line: 2, col: 1, kind: ErrorKind::NoColon } }];
+        w.write(3, &fields(PASSPORT), &reasons).unwrap();
+        w.write(5, &fields("byr:1990 xyz:1"), &[]).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_jsonl() {
+        assert_eq!(export(Format::Jsonl, false), format!("{}\n{}\n",
+            r##"{"byr":"1980","iyr":"2012","eyr":"2030","hgt":"74in","hcl":"#623a2f","ecl":"grn","pid":"087499704"}"##,
+            r##"{"byr":"1990","xyz":"1"}"##));
+        let with_reasons = export(Format::Jsonl, true);
+        assert!(with_reasons.lines().next().unwrap().ends_with(
+            r##""_line":3,"_reasons":["cid: missing","\"a,\\\"b\\\"\" at line 2, column 1: not a key:value pair"]}"##));
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(export(Format::Csv, false),
+                   "byr,iyr,eyr,hgt,hcl,ecl,pid,cid,_extra\n1980,2012,2030,74in,#623a2f,grn,087499704,,\n1990,,,,,,,,xyz:1\n");
+        let with_reasons = export(Format::Csv, true);
+        let lines : Vec<&str> = with_reasons.lines().collect();
+        assert_eq!(lines[0], "byr,iyr,eyr,hgt,hcl,ecl,pid,cid,_extra,_line,_reasons");
+        assert_eq!(lines[1], r#"1980,2012,2030,74in,#623a2f,grn,087499704,,,3,"cid: missing; ""a,\""b\"""" at line 2, column 1: not a key:value pair""#);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &format in &[Format::Passport, Format::Jsonl, Format::Csv] {
+            for &with_reasons in &[false, true] {
+                let text = export(format, with_reasons);
+                let r = records(&text, format);
+                assert_eq!(r.len(), 2, "{:?}", format);
+                assert_eq!(r[0].lines, vec![NORMALISED.to_string()], "{:?}", format);
+                assert_eq!(r[1].lines, vec!["byr:1990 xyz:1".to_string()], "{:?}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let r = records("{\"byr\":\"1990\"}\n\n{\"pid\":\"1\"}\n", Format::Jsonl);
+        assert_eq!(r[0].line, 1);
+        assert_eq!(r[1].line, 3);
+        let r = records("byr,pid\n1990,1\r\n", Format::Csv);
+        assert_eq!(r, vec![Record { line: 2, lines: vec!["byr:1990 pid:1".to_string()] }]);
+        let r = records("a:1\n\n\nb:2\n", Format::Passport);
+        assert_eq!(r[1], Record { line: 4, lines: vec!["b:2".to_string()] });
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let bad = |text : &str, format| read(text.as_bytes(), format).any(|r| r.is_err());
+        assert!(bad("{\"byr\":1990}\n", Format::Jsonl));
+        assert!(bad("not json\n", Format::Jsonl));
+        assert!(bad("byr,pid\n1990\n", Format::Csv));
+        assert!(bad("byr,pid\n\"1990,1\n", Format::Csv));
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("csv".parse(), Ok(Format::Csv));
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
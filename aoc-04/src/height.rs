@@ -0,0 +1,187 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Cm,
+    In,
+    M,
+}
+
+impl Unit {
+    // Nanometres in one of the unit, so every height converts exactly.
+    fn nanometres(self) -> u64 {
+        match self {
+            Unit::Cm => 10_000_000,
+            Unit::In => 25_400_000,
+            Unit::M => 1_000_000_000,
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = ();
+
+    fn from_str(s : &str) -> Result<Unit, ()> {
+        match s {
+            "cm" => Ok(Unit::Cm),
+            "in" => Ok(Unit::In),
+            "m" => Ok(Unit::M),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Unit::Cm => "cm",
+            Unit::In => "in",
+            Unit::M => "m",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// A number directly followed by cm, in or m, like 183cm, 74in or 1.83m.
+// Only metres take a fraction, of up to three decimals, so every height is a
+// whole number of millimetres or inches. Heights compare by length, whatever
+// their unit.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Height {
+    n : u32,
+    // Thousandths of the unit after n, always 0 unless the unit is m.
+    milli : u32,
+    pub unit : Unit,
+}
+
+impl Height {
+    #[cfg(test)]
+    pub fn whole(n : u32, unit : Unit) -> Height {
+        Height { n, milli: 0, unit }
+    }
+
+    // The canonical unit everything is compared in.
+    pub fn nanometres(&self) -> u64 {
+        let per = self.unit.nanometres();
+        self.n as u64 * per + self.milli as u64 * (per / 1000)
+    }
+}
+
+impl PartialEq for Height {
+    fn eq(&self, other : &Height) -> bool {
+        self.nanometres() == other.nanometres()
+    }
+}
+
+impl Eq for Height {}
+
+impl PartialOrd for Height {
+    fn partial_cmp(&self, other : &Height) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Height {
+    fn cmp(&self, other : &Height) -> Ordering {
+        self.nanometres().cmp(&other.nanometres())
+    }
+}
+
+impl FromStr for Height {
+    type Err = ();
+
+    fn from_str(s : &str) -> Result<Height, ()> {
+        let split = s.find(|c : char| !c.is_ascii_digit() && c != '.').ok_or(())?;
+        let (num, unit) = s.split_at(split);
+        let unit = unit.parse()?;
+        let (whole, frac) = match num.split_once('.') {
+            Some((whole, frac)) if unit == Unit::M && !frac.is_empty() => (whole, frac),
+            Some(_) => return Err(()),
+            None => (num, ""),
+        };
+        // parse() alone would also take a sign.
+        if whole.is_empty() || frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(());
+        }
+        let n = whole.parse().map_err(|_| ())?;
+        let milli = format!("{:0<3}", frac).parse().map_err(|_| ())?;
+        Ok(Height { n, milli, unit })
+    }
+}
+
+impl TryFrom<String> for Height {
+    type Error = String;
+
+    fn try_from(s : String) -> Result<Height, String> {
+        s.parse().map_err(|_| format!("invalid height {:?}, expected a number followed by cm, in or m", s))
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        if self.milli == 0 {
+            return write!(f, "{}{}", self.n, self.unit);
+        }
+        let frac = format!("{:03}", self.milli);
+        write!(f, "{}.{}{}", self.n, frac.trim_end_matches('0'), self.unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(s : &str) -> Height {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(h("183cm").unit, Unit::Cm);
+        assert_eq!(h("183cm").to_string(), "183cm");
+        assert_eq!(h("2m").unit, Unit::M);
+        assert_eq!(h("074in").to_string(), "74in");
+        assert_eq!(h("1.83m").to_string(), "1.83m");
+        assert_eq!(h("1.830m").to_string(), "1.83m");
+        assert_eq!(h("1.0m").to_string(), "1m");
+        assert_eq!(h("0.005m").to_string(), "0.005m");
+    }
+
+    #[test]
+    fn test_trailing_garbage() {
+        for bad in &["170cmXYZ", "170cm ", "74inch", "74in2", "2mm", "170", "cm", "1.m", ".5m", "1..5m", "1.2.3m",
+                     "1.2345m", "1.-5m", "1.+5m", "149.9cm", "7.5in", "170.0cm", "+170cm", "-170cm", " 170cm", "170 cm", "99999999999cm"] {
+            assert!(bad.parse::<Height>().is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(h("1m"), h("100cm"));
+        assert_eq!(h("254cm"), h("100in"));
+        assert_eq!(h("76in").nanometres(), 1_930_400_000);
+        assert!(h("59in") < h("150cm"));
+        assert!(h("193cm") < h("76in"));
+        assert_eq!(h("1.83m"), h("183cm"));
+        assert_eq!(h("1.524m"), h("60in"));
+        assert!(h("1.93m") < h("76in"));
+        assert!(h("1.931m") > h("193cm"));
+    }
+
+    #[test]
+    fn test_deserialize() {
+        #[derive(Deserialize)]
+        struct T {
+            h : Height,
+        }
+        let t : T = toml::from_str("h = \"59in\"").unwrap();
+        assert_eq!(t.h, Height::whole(59, Unit::In));
+        assert!(toml::from_str::<T>("h = \"59\"").is_err());
+    }
+}
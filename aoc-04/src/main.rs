@@ -1,178 +1,447 @@
-use std::collections::HashMap;
+use std::env;
+use std::fs::File;
 use std::io;
-use std::io::prelude::*;
-use regex::Regex;
+use std::io::BufWriter;
+use std::path::Path;
+use std::process;
+use std::thread;
+
+mod batch;
+#[cfg(any(test, feature = "bench"))]
+mod bench;
+mod engine;
+mod export;
+mod height;
+mod repair;
+mod profile;
+mod report;
+mod rules;
+mod schema;
+mod strict;
+mod token;
+mod typed;
+
+use export::Format;
+use profile::Profiles;
+use repair::Fix;
+use report::{Problem, Report};
+use schema::Schema;
+use strict::{Action, Strictness};
+use typed::TypedPassport;
 
 struct Passport<'a> {
-    fields: HashMap<String, String>,
-    necessary_fields: Vec<&'a str>,
-    optional_fields: Vec<&'a str>,
+    fields: TypedPassport<'a>,
+    schema: &'a Schema,
+    strictness: Strictness,
+    // The input line the next parse_line() starts on.
+    line: usize,
+    // Malformed tokens and duplicate fields seen by parse_line(), along with
+    // what strictness says to do about them.
+    issues: Vec<(Action, Problem)>,
 }
 
 impl<'a> Passport<'a> {
+    #[cfg(test)]
     fn new() -> Self {
+        Passport::with_schema(Schema::default_schema())
+    }
+
+    fn with_schema(schema: &'a Schema) -> Self {
         Passport {
-            fields: HashMap::new(),
-            necessary_fields: ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"].to_vec(),
-            optional_fields: ["cid"].to_vec(),
+            fields: TypedPassport::new(schema),
+            schema,
+            strictness: Strictness::default(),
+            line: 1,
+            issues: Vec::new(),
         }
     }
 
+    fn set_strictness(&mut self, strictness: Strictness) {
+        self.strictness = strictness;
+    }
+
+    fn set_first_line(&mut self, line: usize) {
+        self.line = line;
+    }
+
     fn add_field(&mut self, f: &str, v: &str) {
-        self.fields.insert(f.to_string(), v.to_string());
+        self.fields.insert(f, v);
+    }
+
+    fn note(&mut self, action: Action, problem: Problem) {
+        if action != Action::Ignore {
+            self.issues.push((action, problem));
+        }
     }
 
+    // A repeated field keeps its last value.
     fn parse_line(&mut self, line: &str) {
-        for entry in line.split(' ') {
-            let e : Vec<&str> = entry.split(':').collect();
+        let first = self.line;
+        self.line += line.matches('\n').count() + 1;
+        for t in token::tokenize(line, first) {
+            match t {
+                Ok(t) => {
+                    if self.fields.contains(t.key) {
+                        let problem = Problem::Duplicate { field: t.key.to_string(), value: t.value.to_string() };
+                        self.note(self.strictness.duplicate, problem);
+                    }
+                    self.add_field(t.key, t.value);
+                }
+                Err(error) => self.note(self.strictness.malformed, Problem::Malformed { error }),
+            }
+        }
+    }
 
-            self.add_field(e[0], e[1]);
+    // Parse issues and unknown fields that strictness handles with action.
+    fn problems(&self, action: Action) -> Vec<Problem> {
+        let mut res : Vec<Problem> = self.issues.iter()
+            .filter(|(a, _)| *a == action)
+            .map(|(_, p)| p.clone())
+            .collect();
+        if self.strictness.unknown == action {
+            for (k, v) in self.fields.unknown() {
+                res.push(Problem::Unknown { field: k.to_string(), value: v.to_string() });
+            }
         }
+        res
     }
 
-    fn min_valid_len(&self) -> usize {
-        return self.necessary_fields.len();
+    fn warnings(&self) -> Vec<Problem> {
+        self.problems(Action::Warn)
     }
 
-    fn field_count(&self) -> usize {
-        return self.necessary_fields.len() + self.optional_fields.len();
+    fn is_rejected(&self) -> bool {
+        !self.problems(Action::Reject).is_empty()
     }
 
-    fn missing(&self) -> usize {
-        return self.field_count() - self.fields.len();
+    fn is_valid(&self) -> bool {
+        !self.is_rejected() && self.schema.required().all(|r| self.fields.contains(&r.name))
     }
 
-    fn missing_optional(&self) -> usize {
-        let mut missing = 0;
-        for f in &self.optional_fields {
-            if !self.fields.get(*f).is_some() {
-                missing += 1;
-            }
+    fn validate_fields(&self) -> bool {
+        if self.is_rejected() {
+            return false;
         }
-        return missing;
+        // Every value was parsed and range checked as it was added.
+        if !self.schema.fields.iter().filter_map(|r| self.fields.get(&r.name)).all(|f| f.valid) {
+            return false;
+        }
+        self.schema.rules.iter().all(|r| r.check(&self.fields).is_none())
     }
 
-    fn is_valid(&self) -> bool {
-        if self.fields.len() == self.field_count() {
-            return true;
-        } else if self.fields.len() < self.min_valid_len() {
-            return false;
-        } else {
-            return self.missing() == self.missing_optional();
+    fn is_valid_pt2(&self) -> bool {
+        self.is_valid() && self.validate_fields()
+    }
+
+    fn fixes(&self) -> Vec<Fix> {
+        repair::suggest(self.schema, &self.fields)
+    }
+
+    // Replaces every value that has a likely fix, and returns what changed.
+    fn apply_fixes(&mut self) -> Vec<Fix> {
+        let fixes = self.fixes();
+        for f in &fixes {
+            self.add_field(&f.field, &f.to);
         }
+        fixes
     }
 
-    fn validate_fields(&self) -> bool {
-        for (k, v) in &self.fields {
-            if k == "byr" {
-                //byr (Birth Year) - four digits; at least 1920 and at most 2002.
-                match v.parse::<i32>() {
-                    Ok(n) => {
-                        if n < 1920 || n > 2002 {
-                            return false;
-                        }
-                    },
-                    Err(_e) => { return false; },
-                }
-            } else if k == "iyr" {
-                //iyr (Issue Year) - four digits; at least 2010 and at most 2020.
-                match v.parse::<i32>() {
-                    Ok(n) => {
-                        if n < 2010 || n > 2020 {
-                            return false;
-                        }
-                    },
-                    Err(_e) => { return false; },
+    // Unlike is_valid_pt2(), goes on past the first problem. Problems come in
+    // schema order, then cross-field rules, rejected tokens in input order and
+    // rejected unknown fields sorted by name.
+    fn report(&self) -> Report {
+        let mut report = Report::default();
+        for rule in &self.schema.fields {
+            match self.fields.get(&rule.name) {
+                None if rule.required => {
+                    report.problems.push(Problem::Missing { field: rule.name.clone() });
                 }
-            } else if k == "eyr" {
-                //eyr (Expiration Year) - four digits; at least 2020 and at most 2030.
-                match v.parse::<i32>() {
-                    Ok(n) => {
-                        if n < 2020 || n > 2030 {
-                            return false;
-                        }
-                    },
-                    Err(_e) => { return false; },
+                Some(f) if !f.valid => {
+                    report.problems.push(Problem::Invalid {
+                        field: rule.name.clone(),
+                        value: f.raw.clone(),
+                        rule: rule.constraint.to_string(),
+                    });
                 }
-            } else if k == "hgt" {
-                //hgt (Height) - a number followed by either cm or in:
-                //    If cm, the number must be at least 150 and at most 193.
-                //    If in, the number must be at least 59 and at most 76.
-                let r_cm = Regex::new(r"^(?P<height>[0-9]{3})cm").unwrap();
-                let r_in = Regex::new(r"^(?P<height>[0-9]{2})in").unwrap();
-                if let Some(m) = r_cm.captures(v) {
-                    let h : i32 = m.name("height").unwrap().as_str().parse().unwrap();
-                    if h < 150 || h > 193 {
-                        return false;
-                    }
-                } else if let Some(m) = r_in.captures(v) {
-                    let h : i32 = m.name("height").unwrap().as_str().parse().unwrap();
-                    if h < 59 || h > 76 {
-                        return false;
-                    }
-                } else {
-                    return false;
-                }
-            } else if k == "hcl" {
-                //hcl (Hair Color) - a # followed by exactly six characters 0-9 or a-f.
-                let r = Regex::new(r"#[0-9a-f]{6}$").unwrap();
-                if !r.is_match(v) {
-                    return false;
-                }
-            } else if k == "ecl" {
-                //ecl (Eye Color) - exactly one of: amb blu brn gry grn hzl oth.
-                if !["amb".to_string(), "blu".to_string(), "brn".to_string(), "gry".to_string(), "grn".to_string(), "hzl".to_string(), "oth".to_string()].contains(&v) {
-                    return false;
+                _ => {}
+            }
+        }
+        report.problems.extend(self.schema.rules.iter().filter_map(|r| r.check(&self.fields)));
+        report.problems.extend(self.problems(Action::Reject));
+        report
+    }
+}
+
+// --profile name=path, given once per profile, replaces the built-in
+// profiles with the ones in those files, matched in the order given.
+// --schema path is a profile named after the file.
+fn load_profiles() -> &'static Profiles {
+    let args : Vec<String> = env::args().collect();
+    let mut specs = Vec::new();
+    for (i, a) in args.iter().enumerate() {
+        let arg = args.get(i + 1).map(|s| s.as_str()).unwrap_or("");
+        match a.as_str() {
+            "--profile" => match arg.split_once('=') {
+                Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+                    specs.push((name.to_string(), path.to_string()));
                 }
-            } else if k == "pid" {
-                //pid (Passport ID) - a nine-digit number, including leading zeroes.
-                let r = Regex::new(r"^[0-9]{9}$").unwrap();
-                if !r.is_match(v) {
-                    return false;
+                _ => {
+                    eprintln!("--profile: expected name=path");
+                    process::exit(1);
                 }
-            } else if k == "cid" {
-                //cid (Country ID) - ignored, missing or not.
-            } else {
-                unreachable!();
+            },
+            "--schema" => {
+                let name = Path::new(arg).file_stem().map_or("custom".into(), |s| s.to_string_lossy());
+                specs.push((name.to_string(), arg.to_string()));
             }
+            _ => {}
+        }
+    }
+    if specs.is_empty() {
+        return Profiles::builtin();
+    }
+    match Profiles::load(&specs) {
+        // Lives for the rest of the program anyway.
+        Ok(p) => Box::leak(Box::new(p)),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
         }
-        return true;
     }
+}
 
-    fn is_valid_pt2(&self) -> bool {
-        return self.is_valid() && self.validate_fields();
+fn parse_action(args: &[String], flag: &str, default: Action) -> Action {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => match args.get(i + 1).map(|s| s.parse()) {
+            Some(Ok(action)) => action,
+            Some(Err(e)) => {
+                eprintln!("{}: {}", flag, e);
+                process::exit(1);
+            }
+            None => {
+                eprintln!("{}: missing action", flag);
+                process::exit(1);
+            }
+        },
+        None => default,
     }
 }
 
-fn get_input<'a>() -> Passport<'a> {
-    let mut passport = Passport::new();
+// --lenient switches every default from reject to warn, and --unknown,
+// --malformed and --duplicate override them one by one.
+fn load_strictness() -> Strictness {
+    let args : Vec<String> = env::args().collect();
+    let base = if args.iter().any(|a| a == "--lenient") { Strictness::lenient() } else { Strictness::strict() };
+    Strictness {
+        unknown: parse_action(&args, "--unknown", base.unknown),
+        malformed: parse_action(&args, "--malformed", base.malformed),
+        duplicate: parse_action(&args, "--duplicate", base.duplicate),
+    }
+}
 
-    let stdin = io::stdin();
-    for line in stdin.lock().lines() {
-        let l = line.unwrap();
-        if l.len() == 0 {
-            break;
+fn parse_count(args: &[String], flag: &str) -> Option<usize> {
+    let i = args.iter().position(|a| a == flag)?;
+    match args.get(i + 1).map(|s| s.parse()) {
+        Some(Ok(n)) => Some(n),
+        _ => {
+            eprintln!("{}: expected a number", flag);
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_format(args: &[String], flag: &str, default: Format) -> Format {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => match args.get(i + 1).map(|s| s.parse()) {
+            Some(Ok(format)) => format,
+            Some(Err(e)) => {
+                eprintln!("{}: {}", flag, e);
+                process::exit(1);
+            }
+            None => {
+                eprintln!("{}: missing format", flag);
+                process::exit(1);
+            }
+        },
+        None => default,
+    }
+}
+
+fn open_export<'a>(args: &[String], flag: &str, format: Format, columns: Vec<&'a str>, with_reasons: bool)
+        -> Option<export::Writer<'a, BufWriter<File>>> {
+    let i = args.iter().position(|a| a == flag)?;
+    let path = match args.get(i + 1) {
+        Some(p) => p,
+        None => {
+            eprintln!("{}: missing file name", flag);
+            process::exit(1);
+        }
+    };
+    let res = File::create(path).and_then(|f| export::Writer::new(BufWriter::new(f), format, columns, with_reasons));
+    match res {
+        Ok(w) => Some(w),
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
         }
-        passport.parse_line(&l);
     }
-    return passport;
 }
 
+#[cfg(feature = "bench")]
+fn run_bench(n: usize, threads: usize) {
+    bench::run(n, threads);
+}
+
+#[cfg(not(feature = "bench"))]
+fn run_bench(_n: usize, _threads: usize) {
+    eprintln!("--bench: built without the bench feature, try cargo run --release --features bench");
+    process::exit(1);
+}
+
+// Records are read, checked and printed this many at a time, so memory use
+// does not grow with the input.
+const CHUNK: usize = 4096;
+
 fn main() {
+    let args : Vec<String> = env::args().collect();
+    let threads = parse_count(&args, "--threads")
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+    if let Some(n) = parse_count(&args, "--bench") {
+        run_bench(n, threads);
+        return;
+    }
+
+    let profiles = load_profiles();
+    let strictness = load_strictness();
+    let show_report = args.iter().any(|a| a == "--report");
+    let show_typed = args.iter().any(|a| a == "--typed");
+    let input_format = parse_format(&args, "--format", Format::Passport);
+    let export_format = parse_format(&args, "--export-format", Format::Jsonl);
+    let mut export_valid = open_export(&args, "--export-valid", export_format, profiles.columns(), false);
+    let mut export_invalid = open_export(&args, "--export-invalid", export_format, profiles.columns(), true);
+    // With --apply-fixes every passport is validated with its likely fixes
+    // in place, and --export-fixed writes the ones that changed, corrected.
+    let mode = if args.iter().any(|a| a == "--apply-fixes") { repair::Mode::Apply } else { repair::Mode::Suggest };
+    let mut export_fixed = open_export(&args, "--export-fixed", export_format, profiles.columns(), false);
+    if export_fixed.is_some() && mode != repair::Mode::Apply {
+        eprintln!("--export-fixed: only works with --apply-fixes");
+        process::exit(1);
+    }
     println!("Enter passport");
+
     let mut count_p1 = 0;
     let mut count_p2 = 0;
-    loop {
-        let p = get_input();
-        count_p1 += if p.is_valid() { 1 } else { 0 };
-        count_p2 += if p.is_valid_pt2() { 1 } else { 0 };
-        println!("{}, {}", count_p1, count_p2);
+    // Each profile has its own batch rules, over the records of that type.
+    let mut batch_states : Vec<rules::BatchState> = profiles.profiles.iter().map(|p| rules::BatchState::new(&p.schema.batch)).collect();
+    let mut by_profile = vec![0; profiles.profiles.len()];
+    let mut count = 0;
+    let stdin = io::stdin();
+    let mut records = export::read(stdin.lock(), input_format);
+    let mut error = None;
+    while error.is_none() {
+        let mut chunk = Vec::with_capacity(CHUNK);
+        for r in records.by_ref() {
+            match r {
+                Ok(r) => chunk.push(r),
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+            if chunk.len() == CHUNK {
+                break;
+            }
+        }
+        if chunk.is_empty() {
+            break;
+        }
+        count += chunk.len();
+        let checked = engine::check_all(profiles, strictness, mode, &chunk, threads);
+        for (record, c) in chunk.iter().zip(checked) {
+            let p = &c.passport;
+            let profile = profiles.profiles.iter().position(|p| std::ptr::eq(p, c.profile)).unwrap();
+            for w in p.warnings() {
+                eprintln!("Warning, passport at line {}: {}", record.line, w);
+            }
+
+            // Batch rules only count against part 2, like the per-field rules.
+            let batch_problems = batch_states[profile].check(record.line, &p.fields);
+            count_p1 += if c.part1 { 1 } else { 0 };
+            let valid = c.part2 && batch_problems.is_empty();
+            count_p2 += if valid { 1 } else { 0 };
+            by_profile[profile] += if valid { 1 } else { 0 };
+            let mut r = c.report;
+            r.problems.extend(batch_problems);
+            if show_report && r.is_ok() {
+                println!("Valid {} at line {}", c.profile.name, record.line);
+            }
+            if show_report && !r.is_ok() {
+                print!("Invalid document at line {}, closest to {}:\n{}", record.line, c.profile.name, r);
+                if mode == repair::Mode::Suggest {
+                    for f in &c.fixes {
+                        println!("  suggestion: {}", f);
+                    }
+                }
+            }
+            if mode == repair::Mode::Apply && !c.fixes.is_empty() {
+                println!("Fixed passport at line {}:", record.line);
+                for f in &c.fixes {
+                    println!("  {}", f);
+                }
+                if let Some(w) = &mut export_fixed {
+                    if let Err(e) = w.write(record.line, &p.fields, &[]) {
+                        eprintln!("Export error: {}", e);
+                        process::exit(1);
+                    }
+                }
+            }
+            let (export, reasons) = if valid { (&mut export_valid, &[][..]) } else { (&mut export_invalid, &r.problems[..]) };
+            if let Some(w) = export {
+                if let Err(e) = w.write(record.line, &p.fields, reasons) {
+                    eprintln!("Export error: {}", e);
+                    process::exit(1);
+                }
+            }
+            if show_typed {
+                let errors = p.fields.errors();
+                if errors.is_empty() {
+                    println!("{}", p.fields);
+                } else {
+                    println!("Passport at line {} has bad fields:", record.line);
+                    for e in errors {
+                        println!("  {}", e);
+                    }
+                }
+            }
+        }
+    }
+    // Whatever came before a read error has been printed and exported.
+    for w in export_valid.iter_mut().chain(export_invalid.iter_mut()).chain(export_fixed.iter_mut()) {
+        if let Err(e) = w.flush() {
+            eprintln!("Export error: {}", e);
+            process::exit(1);
+        }
+    }
+    if let Some(e) = error {
+        eprintln!("Read error: {}", e);
+        process::exit(1);
+    }
+    println!("Passports: {}", count);
+    println!("Valid (part 1): {}", count_p1);
+    println!("Valid (part 2): {}", count_p2);
+    if profiles.profiles.len() > 1 {
+        for (profile, n) in profiles.profiles.iter().zip(by_profile) {
+            println!("  {}: {}", profile.name, n);
+        }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+    use token::{ErrorKind, TokenError};
 
     #[test]
     fn valid_passport() {
@@ -233,6 +502,172 @@ mod tests {
         assert_eq!(p8.validate_fields(), true);
     }
 
+    #[test]
+    fn report_lists_every_problem() {
+        let mut p = Passport::new();
+        p.parse_line("hgt:59cm ecl:zzz eyr:2038 hcl:74454a iyr:2023 pid:3556412378 byr:2007 xyz:1");
+        let r = p.report();
+        assert!(!r.is_ok());
+        let fields : Vec<String> = r.problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(fields, vec![
+            "byr: \"2007\" is not an integer from 1920 to 2002",
+            "iyr: \"2023\" is not an integer from 2010 to 2020",
+            "eyr: \"2038\" is not an integer from 2020 to 2030",
+            "hgt: \"59cm\" is not a height from 59in to 76in",
+            "hcl: \"74454a\" is not a colour like #1a2b3c",
+            "ecl: \"zzz\" is not one of amb, blu, brn, gry, grn, hzl, oth",
+            "pid: \"3556412378\" is not a 9-digit number",
+            "xyz: unknown field (value \"1\")",
+        ]);
+    }
+
+    #[test]
+    fn report_missing_fields() {
+        let mut p = Passport::new();
+        p.parse_line("hcl:#cfa07d eyr:2025 pid:166559648 iyr:2011 ecl:brn hgt:59in");
+        let r = p.report();
+        assert_eq!(r.problems, vec![Problem::Missing { field: "byr".to_string() }]);
+
+        let mut p = Passport::new();
+        p.parse_line("pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f");
+        assert!(p.report().is_ok());
+    }
+
+    #[test]
+    fn typed_keeps_raw_fields() {
+        let mut p = Passport::new();
+        p.parse_line("hgt:170 pid:1 byr:1990");
+        assert_eq!(p.fields.errors().len(), 6);
+        assert_eq!(p.fields.raw("hgt"), Some("170"));
+        assert_eq!(p.fields.value("byr"), Some(&typed::Value::Int(1990)));
+
+        let mut p = Passport::new();
+        p.parse_line("eyr:2029 ecl:blu cid:129 byr:1989 iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm");
+        assert!(p.fields.errors().is_empty());
+        assert_eq!(p.fields.value("hgt"), Some(&typed::Value::Height(typed::Height::whole(165, height::Unit::Cm))));
+        assert_eq!(p.fields.to_string(), "byr:1989 iyr:2014 eyr:2029 hgt:165cm hcl:#a97842 ecl:blu pid:896056539 cid:129");
+    }
+
+    fn with_strictness(strictness: Strictness, line: &str) -> Passport<'static> {
+        let mut p = Passport::new();
+        p.set_strictness(strictness);
+        p.parse_line(line);
+        p
+    }
+
+    const VALID : &str = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f";
+
+    #[test]
+    fn unknown_fields() {
+        let line = format!("{} xyz:1", VALID);
+        let mut s = Strictness::strict();
+
+        let p = with_strictness(s, &line);
+        assert!(!p.is_valid());
+        assert!(!p.validate_fields());
+        assert_eq!(p.report().problems, vec![Problem::Unknown { field: "xyz".to_string(), value: "1".to_string() }]);
+
+        s.unknown = Action::Warn;
+        let p = with_strictness(s, &line);
+        assert!(p.is_valid_pt2());
+        assert!(p.report().is_ok());
+        assert_eq!(p.warnings(), vec![Problem::Unknown { field: "xyz".to_string(), value: "1".to_string() }]);
+
+        s.unknown = Action::Ignore;
+        let p = with_strictness(s, &line);
+        assert!(p.is_valid_pt2());
+        assert!(p.warnings().is_empty());
+    }
+
+    #[test]
+    fn malformed_tokens() {
+        let line = format!("{} oops :x\tpid:", VALID);
+        let mut s = Strictness::strict();
+        let malformed : Vec<Problem> = vec![(ErrorKind::NoColon, "oops", 71), (ErrorKind::EmptyKey, ":x", 76), (ErrorKind::EmptyValue, "pid:", 79)]
+            .into_iter()
+            .map(|(kind, token, col)| Problem::Malformed { error: TokenError { token: token.to_string(), line: 1, col, kind } })
+            .collect();
+
+        let p = with_strictness(s, &line);
+        assert!(!p.is_valid_pt2());
+        assert_eq!(p.report().problems, malformed);
+
+        s.malformed = Action::Warn;
+        let p = with_strictness(s, &line);
+        assert!(p.is_valid_pt2());
+        assert_eq!(p.warnings(), malformed);
+
+        s.malformed = Action::Ignore;
+        let p = with_strictness(s, &line);
+        assert!(p.is_valid_pt2());
+        assert!(p.warnings().is_empty());
+    }
+
+    #[test]
+    fn duplicate_fields() {
+        let line = format!("{} byr:1990", VALID);
+        let mut s = Strictness::strict();
+        let duplicate = vec![Problem::Duplicate { field: "byr".to_string(), value: "1990".to_string() }];
+
+        let p = with_strictness(s, &line);
+        assert!(!p.is_valid_pt2());
+        assert_eq!(p.report().problems, duplicate);
+
+        s.duplicate = Action::Warn;
+        let p = with_strictness(s, &line);
+        assert!(p.is_valid_pt2());
+        assert_eq!(p.warnings(), duplicate);
+        assert_eq!(p.fields.raw("byr"), Some("1990"));
+
+        s.duplicate = Action::Ignore;
+        let p = with_strictness(s, &line);
+        assert!(p.is_valid_pt2());
+        assert!(p.warnings().is_empty());
+    }
+
+    #[test]
+    fn extra_spaces_and_colons() {
+        let p = with_strictness(Strictness::strict(), &format!("  {}  cid:a:b ", VALID));
+        assert!(p.is_valid_pt2());
+        assert_eq!(p.fields.raw("cid"), Some("a:b"));
+        let p = with_strictness(Strictness::strict(), &VALID.replace(' ', "\t \t"));
+        assert!(p.is_valid_pt2());
+        assert_eq!(p.fields.len(), 7);
+    }
+
+    #[test]
+    fn cross_field_rules() {
+        let schema = Schema::parse(&format!("{}{}", include_str!("../schema.toml"), r#"
+            [[rules]]
+            type = "compare"
+            left = "iyr"
+            op = ">="
+            right = "byr"
+            offset = 16
+
+            [[rules]]
+            type = "compare"
+            left = "eyr"
+            op = ">"
+            right = "iyr"
+        "#)).unwrap();
+
+        let mut p = Passport::with_schema(&schema);
+        p.parse_line(VALID);
+        assert!(p.is_valid_pt2());
+
+        let mut p = Passport::with_schema(&schema);
+        p.parse_line("pid:087499704 hgt:74in ecl:grn iyr:2010 eyr:2010 byr:2000 hcl:#623a2f");
+        assert!(p.is_valid());
+        assert!(!p.validate_fields());
+        let problems : Vec<String> = p.report().problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(problems, vec![
+            "eyr: \"2010\" is not an integer from 2020 to 2030",
+            "iyr >= byr + 16 does not hold (iyr \"2010\", byr \"2000\")",
+            "eyr > iyr does not hold (eyr \"2010\", iyr \"2010\")",
+        ]);
+    }
+
     #[test]
     fn fields_byr() {
         let mut p = Passport::new();
@@ -345,4 +780,126 @@ mod tests {
         p.add_field("pid", "152654852345");
         assert_eq!(p.validate_fields(), false);
     }
+
+    #[test]
+    fn apply_fixes() {
+        let mut p = Passport::new();
+        p.parse_line("hcl:dab227 iyr:2012 ecl:brn hgt:182 pid:0021572410 eyr:2020 byr:1992 cid:277");
+        assert!(!p.is_valid_pt2());
+        assert_eq!(p.fixes().len(), 3);
+        let fixes : Vec<String> = p.apply_fixes().into_iter().map(|f| f.to).collect();
+        assert_eq!(fixes, vec!["182cm", "#dab227", "021572410"]);
+        assert!(p.is_valid_pt2());
+        assert!(p.fixes().is_empty());
+
+        let mut p = Passport::new();
+        p.parse_line("hcl:74454a ecl:zzz");
+        p.apply_fixes();
+        assert_eq!(p.fields.raw("hcl"), Some("#74454a"));
+        assert_eq!(p.fields.raw("ecl"), Some("zzz"));
+    }
+
+    // Tiny xorshift, so the fuzz tests are repeatable without extra crates.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n : usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn pick<'t, T>(&mut self, items : &'t [T]) -> &'t T {
+            &items[self.below(items.len())]
+        }
+    }
+
+    const FORMATS : [Format; 3] = [Format::Passport, Format::Jsonl, Format::Csv];
+
+    #[test]
+    fn fuzz_never_panics() {
+        let mut rng = Rng(0x2020_0404);
+        // Mostly bytes the parsers care about, with some invalid UTF-8.
+        let interesting = b" \t\r\n:#,\"{}[]0123456789cmin.-abcdefxyz\xc3\xa9\xff\x80";
+        for _ in 0..3000 {
+            let len = rng.below(120);
+            let bytes : Vec<u8> = (0..len)
+                .map(|_| if rng.below(8) == 0 { rng.next() as u8 } else { *rng.pick(interesting) })
+                .collect();
+            let text = String::from_utf8_lossy(&bytes);
+            let lines : Vec<&str> = text.split('\n').collect();
+            for t in token::tokenize(&text, 1) {
+                let (line, col) = t.map_or_else(|e| (e.line, e.col), |t| (t.line, t.col));
+                assert!(line >= 1 && line <= lines.len());
+                assert!(col <= lines[line - 1].chars().count());
+            }
+            // The readers get the raw bytes as well, invalid UTF-8 and all.
+            for &format in &FORMATS {
+                let records = export::read(&bytes[..], format).chain(export::read(text.as_bytes(), format));
+                for record in records.flatten() {
+                    for strictness in &[Strictness::strict(), Strictness::lenient()] {
+                        let mut p = with_strictness(*strictness, &record.lines.join("\n"));
+                        let _ = (p.is_valid_pt2(), p.report().to_string(), p.fields.errors(), p.warnings());
+                        p.apply_fixes();
+                        for &out in &FORMATS {
+                            let mut buf = Vec::new();
+                            let mut w = export::Writer::new(&mut buf, out, p.schema.columns(), true).unwrap();
+                            w.write(record.line, &p.fields, &p.report().problems).unwrap();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn serialised_passports_round_trip() {
+        let mut rng = Rng(0x5eed_0042);
+        let schema = Schema::default_schema();
+        let chars : Vec<char> = "0123456789abcdefXYZ#:,\"'.é".chars().collect();
+        let separators = [" ", "\t", "  ", " \t ", "\n"];
+        for _ in 0..500 {
+            let mut fields = HashMap::new();
+            for rule in &schema.fields {
+                if fields.is_empty() || rng.below(3) > 0 {
+                    let value : String = (0..1 + rng.below(12)).map(|_| *rng.pick(&chars)).collect();
+                    fields.insert(rule.name.clone(), value);
+                }
+            }
+
+            // The puzzle format, in any order and with any whitespace.
+            let mut tokens : Vec<String> = fields.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+            for i in (1..tokens.len()).rev() {
+                tokens.swap(i, rng.below(i + 1));
+            }
+            let mut text = rng.pick(&["", " ", "\t"]).to_string();
+            for t in tokens {
+                text.push_str(&t);
+                let sep = rng.pick(&separators);
+                text.push_str(sep);
+            }
+            let p = with_strictness(Strictness::strict(), &text);
+            assert_eq!(p.fields.len(), fields.len(), "{:?}", text);
+            for (k, v) in &fields {
+                assert_eq!(p.fields.raw(k), Some(v.as_str()), "{:?}", text);
+            }
+            assert!(p.issues.is_empty(), "{:?}", text);
+
+            // Every export format reads back the same values.
+            for &format in &FORMATS {
+                let mut buf = Vec::new();
+                let mut w = export::Writer::new(&mut buf, format, schema.columns(), false).unwrap();
+                w.write(1, &p.fields, &[]).unwrap();
+                let records : Vec<batch::Record> = export::read(&buf[..], format).map(|r| r.unwrap()).collect();
+                assert_eq!(records.len(), 1);
+                let q = with_strictness(Strictness::strict(), &records[0].lines.join("\n"));
+                assert_eq!(q.fields.to_string(), p.fields.to_string(), "{:?}", format);
+            }
+        }
+    }
 }
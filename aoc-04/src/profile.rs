@@ -0,0 +1,111 @@
+use std::sync::OnceLock;
+
+use crate::schema::{Schema, SchemaError};
+
+// The document types used when no --profile is given, in the order records
+// are matched against them. A passport with a cid is a passport, without
+// one it is a North Pole Credential.
+const BUILTIN : [(&str, &str); 4] = [
+    ("passport", include_str!("../profiles/passport.toml")),
+    ("north-pole-credential", include_str!("../schema.toml")),
+    ("visa", include_str!("../profiles/visa.toml")),
+    ("crew-id", include_str!("../profiles/crew-id.toml")),
+];
+
+// A named kind of document, with its own schema.
+#[derive(Debug)]
+pub struct Profile {
+    pub name : String,
+    pub schema : Schema,
+}
+
+#[derive(Debug)]
+pub struct Profiles {
+    pub profiles : Vec<Profile>,
+}
+
+impl Profiles {
+    pub fn parse(profiles : &[(&str, &str)]) -> Result<Profiles, SchemaError> {
+        let profiles = profiles.iter()
+            .map(|(name, text)| Ok(Profile { name: name.to_string(), schema: Schema::parse(text)? }))
+            .collect::<Result<_, _>>()?;
+        Ok(Profiles { profiles })
+    }
+
+    pub fn builtin() -> &'static Profiles {
+        static PROFILES : OnceLock<Profiles> = OnceLock::new();
+        PROFILES.get_or_init(|| Profiles::parse(&BUILTIN).unwrap())
+    }
+
+    // Profiles read from files at run time, each given as a name and a
+    // path, in the order given.
+    pub fn load(specs : &[(String, String)]) -> Result<Profiles, String> {
+        let mut profiles : Vec<Profile> = Vec::new();
+        for (name, path) in specs {
+            if profiles.iter().any(|p| &p.name == name) {
+                return Err(format!("profile {} is given twice", name));
+            }
+            let schema = Schema::load(path).map_err(|e| format!("could not load profile {} from {}: {}", name, path, e))?;
+            profiles.push(Profile { name: name.clone(), schema });
+        }
+        Ok(Profiles { profiles })
+    }
+
+    // Every field of every profile, each once, in the order they first
+    // appear.
+    pub fn columns(&self) -> Vec<&str> {
+        let mut res : Vec<&str> = Vec::new();
+        for c in self.profiles.iter().flat_map(|p| p.schema.columns()) {
+            if !res.contains(&c) {
+                res.push(c);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(name : &str) -> Option<&'static Profile> {
+        Profiles::builtin().profiles.iter().find(|p| p.name == name)
+    }
+
+    #[test]
+    fn test_builtin() {
+        let p = Profiles::builtin();
+        let names : Vec<&str> = p.profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["passport", "north-pole-credential", "visa", "crew-id"]);
+        assert_eq!(get("passport").unwrap().schema.required().count(), 8);
+        assert_eq!(get("north-pole-credential").unwrap().schema.required().count(), 7);
+        assert!(get("visa").unwrap().schema.rule("vid").unwrap().required);
+        assert!(get("drivers-license").is_none());
+    }
+
+    #[test]
+    fn test_columns() {
+        let cols = Profiles::builtin().columns();
+        assert_eq!(&cols[..8], &["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"]);
+        assert_eq!(&cols[8..], &["vid", "vty", "iss", "crw", "rol"]);
+    }
+
+    fn spec(name : &str, file : &str) -> (String, String) {
+        (name.to_string(), format!("{}/{}", env!("CARGO_MANIFEST_DIR"), file))
+    }
+
+    #[test]
+    fn test_load() {
+        let p = Profiles::load(&[spec("v", "profiles/visa.toml"), spec("x", "schema.toml")]).unwrap();
+        let names : Vec<&str> = p.profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["v", "x"]);
+        assert_eq!(p.profiles[1].schema.columns().len(), 8);
+        assert!(Profiles::load(&[spec("x", "schema.toml"), spec("x", "profiles/visa.toml")]).unwrap_err().contains("twice"));
+        assert!(Profiles::load(&[spec("y", "profiles/nope.toml")]).unwrap_err().starts_with("could not load profile y"));
+    }
+
+    #[test]
+    fn test_bad_profile() {
+        assert!(Profiles::parse(&[("ok", BUILTIN[0].1), ("bad", "fields = 1")]).is_err());
+    }
+}
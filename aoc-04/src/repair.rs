@@ -0,0 +1,118 @@
+use std::fmt;
+
+use crate::schema::{Constraint, Schema};
+use crate::typed::TypedPassport;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    // Only say what looks fixable.
+    Suggest,
+    // Also change the passport, before it is validated.
+    Apply,
+}
+
+// A likely correction for a value that breaks its field's rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fix {
+    pub field : String,
+    pub from : String,
+    pub to : String,
+    pub reason : &'static str,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?} is probably {:?} ({})", self.field, self.from, self.to, self.reason)
+    }
+}
+
+fn all_digits(s : &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+// Returns None if the slip it looks for cannot be what happened.
+type Guess = fn(&str) -> Option<String>;
+
+// Common slips, tried in order.
+const GUESSES : [(&str, Guess); 4] = [
+    ("missing '#'", |v| if v.starts_with('#') { None } else { Some(format!("#{}", v)) }),
+    ("no unit, probably cm", |v| if all_digits(v) { Some(format!("{}cm", v)) } else { None }),
+    ("one leading zero too many", |v| v.strip_prefix('0').filter(|r| all_digits(r)).map(|r| r.to_string())),
+    ("upper case", |v| if v.bytes().any(|b| b.is_ascii_uppercase()) { Some(v.to_ascii_lowercase()) } else { None }),
+];
+
+// The first guess that turns v into a value constraint accepts.
+fn guess(constraint : &Constraint, v : &str) -> Option<(String, &'static str)> {
+    GUESSES.iter()
+        .filter_map(|(reason, g)| g(v).map(|to| (to, *reason)))
+        .find(|(to, _)| constraint.check(to))
+}
+
+// Fixes for every invalid field the schema knows, in schema order. Fields
+// without an obvious fix are left to the report.
+pub fn suggest(schema : &Schema, passport : &TypedPassport) -> Vec<Fix> {
+    schema.fields.iter()
+        .filter_map(|rule| {
+            let f = passport.get(&rule.name).filter(|f| !f.valid)?;
+            let (to, reason) = guess(&rule.constraint, &f.raw)?;
+            Some(Fix { field: rule.name.clone(), from: f.raw.clone(), to, reason })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passport(pairs : &[(&str, &str)]) -> TypedPassport<'static> {
+        let mut p = TypedPassport::new(Schema::default_schema());
+        for (k, v) in pairs {
+            p.insert(k, v);
+        }
+        p
+    }
+
+    fn fix(field : &str, value : &str) -> Option<Fix> {
+        suggest(Schema::default_schema(), &passport(&[(field, value)])).pop()
+    }
+
+    fn fixed(field : &str, value : &str) -> Option<String> {
+        fix(field, value).map(|f| f.to)
+    }
+
+    #[test]
+    fn test_suggestions() {
+        assert_eq!(fix("hcl", "dab227"), Some(Fix {
+            field: "hcl".to_string(),
+            from: "dab227".to_string(),
+            to: "#dab227".to_string(),
+            reason: "missing '#'",
+        }));
+        assert_eq!(fixed("hgt", "170"), Some("170cm".to_string()));
+        assert_eq!(fixed("pid", "0123456789"), Some("123456789".to_string()));
+        assert_eq!(fixed("hcl", "#ABCDEF"), Some("#abcdef".to_string()));
+        assert_eq!(fixed("ecl", "BRN"), Some("brn".to_string()));
+        assert_eq!(fix("hcl", "dab227").unwrap().to_string(), "hcl: \"dab227\" is probably \"#dab227\" (missing '#')");
+    }
+
+    #[test]
+    fn test_only_plausible_fixes() {
+        // Already valid.
+        assert_eq!(fix("hcl", "#dab227"), None);
+        // The fix would still be out of range or the wrong shape.
+        assert_eq!(fix("hgt", "59"), None);
+        assert_eq!(fix("hcl", "dab22"), None);
+        assert_eq!(fix("pid", "1123456789"), None);
+        assert_eq!(fix("pid", "00123456789"), None);
+        assert_eq!(fix("byr", "twenty"), None);
+        // Not in the schema.
+        assert_eq!(fix("xyz", "1"), None);
+    }
+
+    #[test]
+    fn test_schema_order() {
+        let fields = passport(&[("pid", "0123456789"), ("hcl", "dab227"), ("hgt", "170")]);
+        let order : Vec<String> = suggest(Schema::default_schema(), &fields).into_iter().map(|f| f.field).collect();
+        assert_eq!(order, vec!["hgt", "hcl", "pid"]);
+    }
+}
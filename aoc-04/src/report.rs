@@ -0,0 +1,58 @@
+use std::fmt;
+
+use crate::token::TokenError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    Missing { field : String },
+    Unknown { field : String, value : String },
+    // rule describes what the value should have looked like.
+    Invalid { field : String, value : String, rule : String },
+    Malformed { error : TokenError },
+    // value is the one given after the first.
+    Duplicate { field : String, value : String },
+    // A cross-field rule that does not hold, with the fields it looked at.
+    CrossField { rule : String, values : Vec<(String, String)> },
+    // An earlier passport, starting at first_line, has the same value.
+    NotUnique { field : String, value : String, first_line : usize },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Missing { field } => write!(f, "{}: missing", field),
+            Problem::Unknown { field, value } => write!(f, "{}: unknown field (value {:?})", field, value),
+            Problem::Invalid { field, value, rule } => write!(f, "{}: {:?} is not {}", field, value, rule),
+            Problem::Malformed { error } => write!(f, "{}", error),
+            Problem::Duplicate { field, value } => write!(f, "{}: given more than once (again as {:?})", field, value),
+            Problem::CrossField { rule, values } => {
+                let values : Vec<String> = values.iter().map(|(k, v)| format!("{} {:?}", k, v)).collect();
+                write!(f, "{} does not hold ({})", rule, values.join(", "))
+            }
+            Problem::NotUnique { field, value, first_line } => {
+                write!(f, "{}: {:?} already used by the passport at line {}", field, value, first_line)
+            }
+        }
+    }
+}
+
+// Everything wrong with one passport.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub problems : Vec<Problem>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        for p in &self.problems {
+            writeln!(f, "  {}", p)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::report::Problem;
+use crate::typed::TypedPassport;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Op {
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+}
+
+impl Op {
+    fn holds(self, a : i64, b : i64) -> bool {
+        match self {
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// A rule over several fields of the same passport.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CrossRule {
+    // left op right + offset, comparing the fields as integers.
+    Compare {
+        left : String,
+        op : Op,
+        right : String,
+        #[serde(default)]
+        offset : i64,
+    },
+}
+
+impl fmt::Display for CrossRule {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrossRule::Compare { left, op, right, offset } => {
+                write!(f, "{} {} {}", left, op, right)?;
+                match offset {
+                    0 => Ok(()),
+                    n if *n < 0 => write!(f, " - {}", -n),
+                    n => write!(f, " + {}", n),
+                }
+            }
+        }
+    }
+}
+
+impl CrossRule {
+    pub fn fields(&self) -> Vec<&str> {
+        match self {
+            CrossRule::Compare { left, right, .. } => vec![left, right],
+        }
+    }
+
+    // Fields that are missing or not integers are left to the per-field
+    // rules, so the rule only fails if it can actually be evaluated.
+    pub fn check(&self, passport : &TypedPassport) -> Option<Problem> {
+        match self {
+            CrossRule::Compare { left, op, right, offset } => {
+                let a = passport.value(left)?.as_int()?;
+                let b = passport.value(right)?.as_int()?;
+                if op.holds(a, b.saturating_add(*offset)) {
+                    return None;
+                }
+                let raw = |name : &String| (name.clone(), passport.raw(name).unwrap_or_default().to_string());
+                Some(Problem::CrossField { rule: self.to_string(), values: vec![raw(left), raw(right)] })
+            }
+        }
+    }
+}
+
+// A rule over every passport in the input.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BatchRule {
+    // No two passports may share a value for field.
+    Unique { field : String },
+}
+
+impl BatchRule {
+    pub fn fields(&self) -> Vec<&str> {
+        match self {
+            BatchRule::Unique { field } => vec![field],
+        }
+    }
+}
+
+// Remembers what earlier passports looked like, for the batch rules.
+pub struct BatchState<'a> {
+    rules : &'a [BatchRule],
+    // For each rule, the values seen so far and the line they were first
+    // seen on.
+    seen : Vec<HashMap<String, usize>>,
+}
+
+impl<'a> BatchState<'a> {
+    pub fn new(rules : &'a [BatchRule]) -> BatchState<'a> {
+        BatchState { rules, seen: rules.iter().map(|_| HashMap::new()).collect() }
+    }
+
+    // Checks the passport starting at line against all earlier ones. Values
+    // are compared in their canonical form, so 183cm and 1830mm would be
+    // the same height if the schema allowed both.
+    pub fn check(&mut self, line : usize, passport : &TypedPassport) -> Vec<Problem> {
+        let mut res = Vec::new();
+        for (rule, seen) in self.rules.iter().zip(self.seen.iter_mut()) {
+            match rule {
+                BatchRule::Unique { field } => {
+                    if let Some(f) = passport.get(field) {
+                        let key = f.value.as_ref().map_or(f.raw.clone(), |v| v.to_string());
+                        let first = *seen.entry(key).or_insert(line);
+                        if first != line {
+                            res.push(Problem::NotUnique { field: field.clone(), value: f.raw.clone(), first_line: first });
+                        }
+                    }
+                }
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+    use std::sync::OnceLock;
+
+    fn schema() -> &'static Schema {
+        static SCHEMA : OnceLock<Schema> = OnceLock::new();
+        SCHEMA.get_or_init(|| {
+            let int = |name : &str| format!("[[fields]]\nname = \"{}\"\ntype = \"int\"\nmin = 0\nmax = 9999\n", name);
+            let text : String = ["byr", "iyr", "eyr", "a", "b"].iter().map(|n| int(n)).collect();
+            Schema::parse(&format!("{}[[fields]]\nname = \"pid\"\ntype = \"id\"\ndigits = 9\n", text)).unwrap()
+        })
+    }
+
+    fn fields(pairs : &[(&str, &str)]) -> TypedPassport<'static> {
+        let mut p = TypedPassport::new(schema());
+        for (k, v) in pairs {
+            p.insert(k, v);
+        }
+        p
+    }
+
+    fn compare(left : &str, op : Op, right : &str, offset : i64) -> CrossRule {
+        CrossRule::Compare { left: left.to_string(), op, right: right.to_string(), offset }
+    }
+
+    #[test]
+    fn test_compare() {
+        let rule = compare("iyr", Op::Ge, "byr", 16);
+        assert_eq!(rule.to_string(), "iyr >= byr + 16");
+        assert_eq!(rule.check(&fields(&[("byr", "1990"), ("iyr", "2006")])), None);
+        assert_eq!(rule.check(&fields(&[("byr", "1990"), ("iyr", "2005")])), Some(Problem::CrossField {
+            rule: "iyr >= byr + 16".to_string(),
+            values: vec![("iyr".to_string(), "2005".to_string()), ("byr".to_string(), "1990".to_string())],
+        }));
+
+        let rule = compare("eyr", Op::Gt, "iyr", 0);
+        assert_eq!(rule.to_string(), "eyr > iyr");
+        assert!(rule.check(&fields(&[("iyr", "2015"), ("eyr", "2015")])).is_some());
+        assert!(rule.check(&fields(&[("iyr", "2015"), ("eyr", "2016")])).is_none());
+        assert_eq!(compare("a", Op::Ne, "b", -2).to_string(), "a != b - 2");
+    }
+
+    #[test]
+    fn test_compare_skips_unusable_fields() {
+        let rule = compare("eyr", Op::Gt, "iyr", 0);
+        assert!(rule.check(&fields(&[("eyr", "2015")])).is_none());
+        assert!(rule.check(&fields(&[("eyr", "2015"), ("iyr", "soon")])).is_none());
+    }
+
+    #[test]
+    fn test_unique() {
+        let rules = vec![BatchRule::Unique { field: "pid".to_string() }];
+        let mut state = BatchState::new(&rules);
+        assert!(state.check(1, &fields(&[("pid", "000000001")])).is_empty());
+        assert!(state.check(4, &fields(&[("pid", "000000002")])).is_empty());
+        assert!(state.check(7, &fields(&[("byr", "1990")])).is_empty());
+        assert_eq!(state.check(9, &fields(&[("pid", "000000001")])), vec![Problem::NotUnique {
+            field: "pid".to_string(),
+            value: "000000001".to_string(),
+            first_line: 1,
+        }]);
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(test)]
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::height::Height;
+use crate::rules::{BatchRule, CrossRule};
+use crate::typed::Value;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Schema {
+    pub fields : Vec<FieldRule>,
+    #[serde(default)]
+    pub rules : Vec<CrossRule>,
+    #[serde(default)]
+    pub batch : Vec<BatchRule>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FieldRule {
+    pub name : String,
+    #[serde(default)]
+    pub required : bool,
+    #[serde(flatten)]
+    pub constraint : Constraint,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Constraint {
+    Any,
+    Int { min : i64, max : i64 },
+    // regex is compiled from pattern when the schema is loaded, and always
+    // has to match the whole value.
+    Regex {
+        pattern : String,
+        #[serde(skip)]
+        regex : Option<Regex>,
+    },
+    Enum { values : Vec<String> },
+    // Unit suffix to inclusive [min, max].
+    Units { units : BTreeMap<String, [i64; 2]> },
+    // A height in cm, in or m, compared by length against one range.
+    Height { min : Height, max : Height },
+    // # and six lower case hex digits.
+    Color,
+    // Exactly digits digits, leading zeroes included.
+    Id { digits : usize },
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    DuplicateField(String),
+    // A cross-field or batch rule names a field that is not in the schema.
+    UnknownField(String),
+    BadPattern { field : String, error : regex::Error },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Io(e) => write!(f, "{}", e),
+            SchemaError::Parse(e) => write!(f, "{}", e),
+            SchemaError::DuplicateField(name) => write!(f, "field {} is defined twice", name),
+            SchemaError::UnknownField(name) => write!(f, "rule uses undefined field {}", name),
+            SchemaError::BadPattern { field, error } => write!(f, "bad pattern for {}: {}", field, error),
+        }
+    }
+}
+
+impl Constraint {
+    // Converts v to the type the rule works on, if it has the right shape.
+    // Ranges are left to accepts().
+    pub fn parse(&self, v : &str) -> Option<Value> {
+        let digits = |s : &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        match self {
+            Constraint::Any => Some(Value::Text(v.to_string())),
+            Constraint::Int { .. } => v.parse().ok().map(Value::Int),
+            Constraint::Regex { regex, .. } => {
+                if regex.as_ref().unwrap().is_match(v) { Some(Value::Text(v.to_string())) } else { None }
+            }
+            Constraint::Enum { values } => {
+                values.iter().position(|e| e == v).map(|index| Value::Choice { index, name: v.to_string() })
+            }
+            Constraint::Units { units } => {
+                let split = v.find(|c : char| !c.is_ascii_digit()).unwrap_or(v.len());
+                let (num, unit) = v.split_at(split);
+                if !digits(num) || !units.contains_key(unit) {
+                    return None;
+                }
+                num.parse().ok().map(|n| Value::Measure { n, unit: unit.to_string() })
+            }
+            Constraint::Height { .. } => v.parse().ok().map(Value::Height),
+            Constraint::Color => {
+                let hex = v.strip_prefix('#')?;
+                if hex.len() != 6 || !hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+                    return None;
+                }
+                let byte = |i : usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+                Some(Value::Color([byte(0), byte(2), byte(4)]))
+            }
+            Constraint::Id { digits: len } => {
+                if v.len() != *len || !digits(v) {
+                    return None;
+                }
+                v.parse().ok().map(|n| Value::Id { n, digits: *len })
+            }
+        }
+    }
+
+    // Whether a value parse() returned is in range.
+    pub fn accepts(&self, v : &Value) -> bool {
+        match (self, v) {
+            (Constraint::Int { min, max }, Value::Int(n)) => (*min..=*max).contains(n),
+            (Constraint::Units { units }, Value::Measure { n, unit }) => {
+                units.get(unit).is_some_and(|[min, max]| (*min..=*max).contains(n))
+            }
+            (Constraint::Height { min, max }, Value::Height(h)) => (*min..=*max).contains(h),
+            (Constraint::Int { .. }, _) | (Constraint::Units { .. }, _) | (Constraint::Height { .. }, _) => false,
+            _ => true,
+        }
+    }
+
+    // For values that are not part of a passport, like suggested fixes.
+    pub fn check(&self, v : &str) -> bool {
+        self.parse(v).is_some_and(|v| self.accepts(&v))
+    }
+}
+
+// Describes what a valid value looks like, for reports.
+impl fmt::Display for Constraint {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constraint::Any => write!(f, "any value"),
+            Constraint::Int { min, max } => write!(f, "an integer from {} to {}", min, max),
+            Constraint::Regex { pattern, .. } => write!(f, "a value matching {}", pattern),
+            Constraint::Enum { values } => write!(f, "one of {}", values.join(", ")),
+            Constraint::Units { units } => {
+                let ranges : Vec<String> = units.iter()
+                    .map(|(unit, [min, max])| format!("{}-{}{}", min, max, unit))
+                    .collect();
+                write!(f, "a value of {}", ranges.join(" or "))
+            }
+            Constraint::Height { min, max } => write!(f, "a height from {} to {}", min, max),
+            Constraint::Color => write!(f, "a colour like #1a2b3c"),
+            Constraint::Id { digits } => write!(f, "a {}-digit number", digits),
+        }
+    }
+}
+
+impl Schema {
+    pub fn parse(text : &str) -> Result<Schema, SchemaError> {
+        let mut schema : Schema = toml::from_str(text).map_err(SchemaError::Parse)?;
+        for i in 0..schema.fields.len() {
+            let (earlier, rest) = schema.fields.split_at_mut(i);
+            let rule = &mut rest[0];
+            if earlier.iter().any(|r| r.name == rule.name) {
+                return Err(SchemaError::DuplicateField(rule.name.clone()));
+            }
+            // Compiled once here, so checking a value never builds a Regex.
+            if let Constraint::Regex { pattern, regex } = &mut rule.constraint {
+                match Regex::new(&format!("^(?:{})$", pattern)) {
+                    Ok(r) => *regex = Some(r),
+                    Err(error) => return Err(SchemaError::BadPattern { field: rule.name.clone(), error }),
+                }
+            }
+        }
+        let used = schema.rules.iter().flat_map(|r| r.fields()).chain(schema.batch.iter().flat_map(|r| r.fields()));
+        for name in used {
+            if schema.rule(name).is_none() {
+                return Err(SchemaError::UnknownField(name.to_string()));
+            }
+        }
+        Ok(schema)
+    }
+
+    pub fn load<P : AsRef<Path>>(path : P) -> Result<Schema, SchemaError> {
+        let text = fs::read_to_string(path).map_err(SchemaError::Io)?;
+        Schema::parse(&text)
+    }
+
+    // The puzzle rules on their own. Outside tests they are the
+    // north-pole-credential profile.
+    #[cfg(test)]
+    pub fn default_schema() -> &'static Schema {
+        static SCHEMA : OnceLock<Schema> = OnceLock::new();
+        SCHEMA.get_or_init(|| Schema::parse(include_str!("../schema.toml")).unwrap())
+    }
+
+    pub fn rule(&self, name : &str) -> Option<&FieldRule> {
+        self.fields.iter().find(|r| r.name == name)
+    }
+
+    pub fn columns(&self) -> Vec<&str> {
+        self.fields.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    pub fn required(&self) -> impl Iterator<Item = &FieldRule> {
+        self.fields.iter().filter(|r| r.required)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_schema() {
+        let s = Schema::default_schema();
+        assert_eq!(s.required().count(), 7);
+        let optional : Vec<&str> = s.fields.iter().filter(|r| !r.required).map(|r| r.name.as_str()).collect();
+        assert_eq!(optional, vec!["cid"]);
+    }
+
+    #[test]
+    fn test_custom_schema() {
+        let s = Schema::parse(r#"
+            [[fields]]
+            name = "age"
+            required = true
+            type = "int"
+            min = 18
+            max = 99
+
+            [[fields]]
+            name = "note"
+            type = "any"
+        "#).unwrap();
+        assert_eq!(s.required().count(), 1);
+        assert!(s.rule("age").unwrap().constraint.check("18"));
+        assert!(!s.rule("age").unwrap().constraint.check("17"));
+        assert!(s.rule("note").unwrap().constraint.check(""));
+        assert!(s.rule("byr").is_none());
+    }
+
+    #[test]
+    fn test_rules() {
+        let s = Schema::parse(r#"
+            [[fields]]
+            name = "byr"
+            type = "any"
+
+            [[fields]]
+            name = "iyr"
+            type = "any"
+
+            [[rules]]
+            type = "compare"
+            left = "iyr"
+            op = ">="
+            right = "byr"
+            offset = 16
+
+            [[batch]]
+            type = "unique"
+            field = "iyr"
+        "#).unwrap();
+        assert_eq!(s.rules.len(), 1);
+        assert_eq!(s.rules[0].to_string(), "iyr >= byr + 16");
+        assert_eq!(s.batch.len(), 1);
+        assert!(Schema::default_schema().rules.is_empty());
+        assert!(Schema::default_schema().batch.is_empty());
+
+        let bad = "[[fields]]\nname = \"a\"\ntype = \"any\"\n[[batch]]\ntype = \"unique\"\nfield = \"b\"";
+        assert!(matches!(Schema::parse(bad), Err(SchemaError::UnknownField(f)) if f == "b"));
+        let bad = "[[fields]]\nname = \"a\"\ntype = \"any\"\n[[rules]]\ntype = \"compare\"\nleft = \"a\"\nop = \"=<\"\nright = \"a\"";
+        assert!(matches!(Schema::parse(bad), Err(SchemaError::Parse(_))));
+    }
+
+    #[test]
+    fn test_units() {
+        let s = Schema::parse("[[fields]]\nname = \"hgt\"\ntype = \"units\"\nunits = { cm = [150, 193], in = [59, 76] }").unwrap();
+        let hgt = &s.rule("hgt").unwrap().constraint;
+        assert_eq!(hgt.to_string(), "a value of 150-193cm or 59-76in");
+        assert!(hgt.check("150cm"));
+        assert!(hgt.check("76in"));
+        assert!(!hgt.check("77in"));
+        assert!(!hgt.check("170"));
+        assert!(!hgt.check("cm"));
+        assert!(!hgt.check("170mm"));
+        assert!(!hgt.check("170cmXYZ"));
+    }
+
+    #[test]
+    fn test_height() {
+        let hgt = &Schema::default_schema().rule("hgt").unwrap().constraint;
+        for ok in &["150cm", "193cm", "59in", "76in", "1.5m", "1.93m"] {
+            assert!(hgt.check(ok), "{}", ok);
+        }
+        for bad in &["149cm", "194cm", "58in", "77in", "1m", "2m", "1.498m", "1.931m", "149.9cm", "170.5cm", "170", "170cmXYZ",
+                     "74inch", "x170cm", "170 cm"] {
+            assert!(!hgt.check(bad), "{}", bad);
+        }
+        // The one range gives the same answers as the puzzle's two.
+        for n in 0..300 {
+            assert_eq!(hgt.check(&format!("{}cm", n)), (150..=193).contains(&n), "{}cm", n);
+            assert_eq!(hgt.check(&format!("{}in", n)), (59..=76).contains(&n), "{}in", n);
+        }
+        let bad = "[[fields]]\nname = \"h\"\ntype = \"height\"\nmin = \"150\"\nmax = \"2m\"";
+        assert!(matches!(Schema::parse(bad), Err(SchemaError::Parse(_))));
+    }
+
+    #[test]
+    fn test_color_and_id() {
+        let s = Schema::default_schema();
+        let parse = |name, v| s.rule(name).unwrap().constraint.parse(v);
+        assert_eq!(parse("hcl", "#623a2f"), Some(Value::Color([0x62, 0x3a, 0x2f])));
+        assert_eq!(parse("hcl", "x#623a2f"), None);
+        assert_eq!(parse("hcl", "#623a2f0"), None);
+        assert_eq!(parse("hcl", "#623A2F"), None);
+        assert_eq!(parse("hcl", "#623a2f\n"), None);
+        assert_eq!(parse("pid", "087499704"), Some(Value::Id { n: 87499704, digits: 9 }));
+        assert_eq!(parse("pid", "0874997041"), None);
+        assert_eq!(parse("pid", "087499704 "), None);
+        assert_eq!(parse("pid", "+87499704"), None);
+    }
+
+    #[test]
+    fn test_regex_anchored() {
+        // Patterns in a custom schema match the whole value even without ^ and $.
+        let s = Schema::parse("[[fields]]\nname = \"a\"\ntype = \"regex\"\npattern = \"[0-9]+|x\"").unwrap();
+        let a = &s.rule("a").unwrap().constraint;
+        assert!(a.check("123"));
+        assert!(a.check("x"));
+        assert!(!a.check("123x"));
+        assert!(!a.check("x1"));
+    }
+
+    #[test]
+    fn test_describe() {
+        let s = Schema::default_schema();
+        let describe = |name| s.rule(name).unwrap().constraint.to_string();
+        assert_eq!(describe("byr"), "an integer from 1920 to 2002");
+        assert_eq!(describe("hgt"), "a height from 59in to 76in");
+        assert_eq!(describe("ecl"), "one of amb, blu, brn, gry, grn, hzl, oth");
+        assert_eq!(describe("hcl"), "a colour like #1a2b3c");
+        assert_eq!(describe("pid"), "a 9-digit number");
+        assert_eq!(describe("cid"), "any value");
+    }
+
+    #[test]
+    fn test_bad_schemas() {
+        assert!(matches!(Schema::parse("fields = 3"), Err(SchemaError::Parse(_))));
+        assert!(matches!(Schema::parse("[[fields]]\nname = \"a\"\ntype = \"float\""), Err(SchemaError::Parse(_))));
+        let dup = "[[fields]]\nname = \"a\"\ntype = \"any\"\n[[fields]]\nname = \"a\"\ntype = \"any\"";
+        assert!(matches!(Schema::parse(dup), Err(SchemaError::DuplicateField(_))));
+        let bad = "[[fields]]\nname = \"a\"\ntype = \"regex\"\npattern = \"(\"";
+        assert!(matches!(Schema::parse(bad), Err(SchemaError::BadPattern { .. })));
+        assert!(matches!(Schema::load("/nonexistent/schema.toml"), Err(SchemaError::Io(_))));
+    }
+}
@@ -0,0 +1,63 @@
+use std::str::FromStr;
+
+// What to do about input that does not fit the schema.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    // The passport is invalid.
+    Reject,
+    // The passport is judged without it, but the problem is kept as a warning.
+    Warn,
+    Ignore,
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Action, String> {
+        match s {
+            "reject" => Ok(Action::Reject),
+            "warn" => Ok(Action::Warn),
+            "ignore" => Ok(Action::Ignore),
+            _ => Err(format!("unknown action {:?}, expected reject, warn or ignore", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Strictness {
+    // Fields the schema does not know.
+    pub unknown : Action,
+    // Tokens that are not key:value.
+    pub malformed : Action,
+    // A field given more than once. Unless rejected, the last value wins.
+    pub duplicate : Action,
+}
+
+impl Strictness {
+    pub fn strict() -> Strictness {
+        Strictness { unknown: Action::Reject, malformed: Action::Reject, duplicate: Action::Reject }
+    }
+
+    pub fn lenient() -> Strictness {
+        Strictness { unknown: Action::Warn, malformed: Action::Warn, duplicate: Action::Warn }
+    }
+}
+
+impl Default for Strictness {
+    fn default() -> Strictness {
+        Strictness::strict()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!("reject".parse(), Ok(Action::Reject));
+        assert_eq!("warn".parse(), Ok(Action::Warn));
+        assert_eq!("ignore".parse(), Ok(Action::Ignore));
+        assert!("Warn".parse::<Action>().is_err());
+    }
+}
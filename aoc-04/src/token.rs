@@ -0,0 +1,144 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    NoColon,
+    EmptyKey,
+    EmptyValue,
+}
+
+// A token that is not a key:value pair. line and col are where the token
+// starts, both 1-based, with col in characters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenError {
+    pub token : String,
+    pub line : usize,
+    pub col : usize,
+    pub kind : ErrorKind,
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            ErrorKind::NoColon => "not a key:value pair",
+            ErrorKind::EmptyKey => "no key before the ':'",
+            ErrorKind::EmptyValue => "no value after the ':'",
+        };
+        write!(f, "{:?} at line {}, column {}: {}", self.token, self.line, self.col, what)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Token<'a> {
+    pub key : &'a str,
+    // Everything after the first ':', so values may contain ':' themselves.
+    pub value : &'a str,
+    pub line : usize,
+    pub col : usize,
+}
+
+fn token(text : &str, line : usize, col : usize) -> Result<Token<'_>, TokenError> {
+    let error = |kind| Err(TokenError { token: text.to_string(), line, col, kind });
+    match text.split_once(':') {
+        None => error(ErrorKind::NoColon),
+        Some(("", _)) => error(ErrorKind::EmptyKey),
+        Some((_, "")) => error(ErrorKind::EmptyValue),
+        Some((key, value)) => Ok(Token { key, value, line, col }),
+    }
+}
+
+// Splits text into key:value tokens. Any run of whitespace separates
+// tokens, tabs and line breaks included. The text starts on line first.
+pub struct Tokens<'a> {
+    text : &'a str,
+    // Byte offset, line and column of the rest of the text.
+    pos : usize,
+    line : usize,
+    col : usize,
+}
+
+pub fn tokenize(text : &str, first : usize) -> Tokens<'_> {
+    Tokens { text, pos: 0, line: first, col: 1 }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token<'a>, TokenError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut start = None;
+        for (i, c) in self.text[self.pos..].char_indices() {
+            let at = self.pos + i;
+            let (line, col) = (self.line, self.col);
+            if c == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+            match (c.is_whitespace(), start) {
+                (true, None) | (false, Some(_)) => {}
+                (false, None) => start = Some((at, line, col)),
+                (true, Some((from, line, col))) => {
+                    self.pos = at + c.len_utf8();
+                    return Some(token(&self.text[from..at], line, col));
+                }
+            }
+        }
+        self.pos = self.text.len();
+        let (from, line, col) = start?;
+        Some(token(&self.text[from..], line, col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(line : &str) -> Vec<(&str, &str, usize)> {
+        tokenize(line, 1).map(|t| t.unwrap()).map(|t| (t.key, t.value, t.col)).collect()
+    }
+
+    fn kinds(line : &str) -> Vec<Result<&str, (ErrorKind, usize)>> {
+        tokenize(line, 1).map(|t| t.map(|t| t.key).map_err(|e| (e.kind, e.col))).collect()
+    }
+
+    #[test]
+    fn test_whitespace() {
+        assert_eq!(ok("a:1 b:2"), vec![("a", "1", 1), ("b", "2", 5)]);
+        assert_eq!(ok("  a:1\t\tb:2   c:3 "), vec![("a", "1", 3), ("b", "2", 8), ("c", "3", 14)]);
+        assert_eq!(ok(""), vec![]);
+        assert_eq!(ok(" \t\r "), vec![]);
+        assert_eq!(ok("a:1\r"), vec![("a", "1", 1)]);
+    }
+
+    #[test]
+    fn test_colons_in_values() {
+        assert_eq!(ok("cid:a:b x::"), vec![("cid", "a:b", 1), ("x", ":", 9)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(kinds("oops a:1 :x y: :"), vec![
+            Err((ErrorKind::NoColon, 1)),
+            Ok("a"),
+            Err((ErrorKind::EmptyKey, 10)),
+            Err((ErrorKind::EmptyValue, 13)),
+            Err((ErrorKind::EmptyKey, 16)),
+        ]);
+        let e = tokenize("a:1  oops", 4).nth(1).unwrap().unwrap_err();
+        assert_eq!(e.to_string(), "\"oops\" at line 4, column 6: not a key:value pair");
+    }
+
+    #[test]
+    fn test_lines() {
+        let lines : Vec<(usize, usize)> = tokenize("a:1 b:2\n  c:3\r\n\nd", 7)
+            .map(|t| t.map_or_else(|e| (e.line, e.col), |t| (t.line, t.col)))
+            .collect();
+        assert_eq!(lines, vec![(7, 1), (7, 5), (8, 3), (10, 1)]);
+    }
+
+    #[test]
+    fn test_columns_count_characters() {
+        assert_eq!(ok("ñ:é\u{3000}b:2"), vec![("ñ", "é", 1), ("b", "2", 5)]);
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::fmt;
+
+pub use crate::height::Height;
+use crate::schema::Schema;
+
+// A field value converted from its text form, with the type its schema rule
+// gives it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    // Values of any and regex fields.
+    Text(String),
+    // One of the values of an enum field, by its position in the rule's
+    // list. name is kept for the text form.
+    Choice { index : usize, name : String },
+    Int(i64),
+    // A number and one of the unit suffixes of a units field.
+    Measure { n : i64, unit : String },
+    Height(Height),
+    // #rrggbb.
+    Color([u8; 3]),
+    // A number of a fixed width, leading zeroes included.
+    Id { n : u64, digits : usize },
+}
+
+impl Value {
+    // Only integers take part in comparisons between fields.
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+// The canonical text form, which parses back to the same value.
+impl fmt::Display for Value {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Choice { name, .. } => write!(f, "{}", name),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Measure { n, unit } => write!(f, "{}{}", n, unit),
+            Value::Height(h) => write!(f, "{}", h),
+            Value::Color([r, g, b]) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Value::Id { n, digits } => write!(f, "{:0w$}", n, w = *digits),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub raw : String,
+    // None if the schema does not know the field, or the value does not have
+    // the shape its rule needs.
+    pub value : Option<Value>,
+    // The value parsed and is within the rule's range.
+    pub valid : bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    Missing { field : String },
+    Invalid { field : String, value : String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Missing { field } => write!(f, "{}: missing", field),
+            ParseError::Invalid { field, value } => write!(f, "{}: cannot parse {:?}", field, value),
+        }
+    }
+}
+
+// The fields of one passport, each parsed once by its schema rule as it is
+// added. The raw text stays alongside, for reports and lenient mode.
+#[derive(Clone, Debug)]
+pub struct TypedPassport<'s> {
+    schema : &'s Schema,
+    fields : HashMap<String, Field>,
+}
+
+impl<'s> TypedPassport<'s> {
+    pub fn new(schema : &'s Schema) -> TypedPassport<'s> {
+        TypedPassport { schema, fields: HashMap::new() }
+    }
+
+    // Replaces any earlier value of the field.
+    pub fn insert(&mut self, name : &str, raw : &str) {
+        let rule = self.schema.rule(name);
+        let value = rule.and_then(|r| r.constraint.parse(raw));
+        let valid = match (rule, &value) {
+            (Some(r), Some(v)) => r.constraint.accepts(v),
+            _ => false,
+        };
+        self.fields.insert(name.to_string(), Field { raw: raw.to_string(), value, valid });
+    }
+
+    pub fn get(&self, name : &str) -> Option<&Field> {
+        self.fields.get(name)
+    }
+
+    pub fn contains(&self, name : &str) -> bool {
+        self.fields.contains_key(name)
+    }
+
+    pub fn raw(&self, name : &str) -> Option<&str> {
+        self.get(name).map(|f| f.raw.as_str())
+    }
+
+    pub fn value(&self, name : &str) -> Option<&Value> {
+        self.get(name).and_then(|f| f.value.as_ref())
+    }
+
+    // The value in its canonical form, or as given if it did not parse.
+    pub fn canonical(&self, name : &str) -> Option<String> {
+        let f = self.get(name)?;
+        Some(f.value.as_ref().map_or(f.raw.clone(), |v| v.to_string()))
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    // Fields the schema does not know, sorted by name.
+    pub fn unknown(&self) -> Vec<(&str, &str)> {
+        let mut res : Vec<(&str, &str)> = self.fields.iter()
+            .filter(|(k, _)| self.schema.rule(k).is_none())
+            .map(|(k, f)| (k.as_str(), f.raw.as_str()))
+            .collect();
+        res.sort();
+        res
+    }
+
+    // Missing required fields and values without the right shape, in schema
+    // order. Values that parse but are out of range are up to the report.
+    pub fn errors(&self) -> Vec<ParseError> {
+        let mut res = Vec::new();
+        for rule in &self.schema.fields {
+            match self.get(&rule.name) {
+                None if rule.required => res.push(ParseError::Missing { field: rule.name.clone() }),
+                Some(Field { raw, value: None, .. }) => {
+                    res.push(ParseError::Invalid { field: rule.name.clone(), value: raw.clone() });
+                }
+                _ => {}
+            }
+        }
+        res
+    }
+}
+
+// Same format as the input, so a typed passport can be read back in. Fields
+// come in schema order with their canonical values, then unknown fields.
+impl fmt::Display for TypedPassport<'_> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let known = self.schema.fields.iter().filter_map(|r| Some((r.name.as_str(), self.canonical(&r.name)?)));
+        let unknown = self.unknown().into_iter().map(|(k, v)| (k, v.to_string()));
+        let tokens : Vec<String> = known.chain(unknown).map(|(k, v)| format!("{}:{}", k, v)).collect();
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::height::Unit;
+
+    fn typed(line : &str) -> TypedPassport<'static> {
+        let mut p = TypedPassport::new(Schema::default_schema());
+        for e in line.split(' ') {
+            let (k, v) = e.split_once(':').unwrap();
+            p.insert(k, v);
+        }
+        p
+    }
+
+    #[test]
+    fn test_parse() {
+        let p = typed("pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f");
+        assert_eq!(p.value("byr"), Some(&Value::Int(1980)));
+        assert_eq!(p.value("hgt"), Some(&Value::Height(Height::whole(74, Unit::In))));
+        assert_eq!(p.value("hcl"), Some(&Value::Color([0x62, 0x3a, 0x2f])));
+        assert_eq!(p.value("ecl"), Some(&Value::Choice { index: 4, name: "grn".to_string() }));
+        assert_eq!(p.value("pid"), Some(&Value::Id { n: 87499704, digits: 9 }));
+        assert_eq!(p.value("cid"), None);
+        assert!(p.errors().is_empty());
+        assert_eq!(p.to_string(), "byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn pid:087499704");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let p = typed("eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018");
+        assert_eq!(p.errors(), vec![
+            ParseError::Missing { field: "byr".to_string() },
+            ParseError::Invalid { field: "hgt".to_string(), value: "170".to_string() },
+            ParseError::Invalid { field: "pid".to_string(), value: "186cm".to_string() },
+        ]);
+        assert_eq!(p.raw("hgt"), Some("170"));
+        assert!(!p.get("hgt").unwrap().valid);
+    }
+
+    #[test]
+    fn test_out_of_range_still_parses() {
+        let p = typed("hgt:59cm ecl:oth eyr:2038 hcl:#74454a iyr:2023 pid:355641237 byr:2007 cid:x");
+        assert_eq!(p.value("hgt"), Some(&Value::Height(Height::whole(59, Unit::Cm))));
+        assert!(!p.get("hgt").unwrap().valid);
+        assert_eq!(p.value("eyr"), Some(&Value::Int(2038)));
+        assert!(p.get("cid").unwrap().valid);
+        assert!(p.errors().is_empty());
+    }
+
+    #[test]
+    fn test_canonical() {
+        let p = typed("byr:+1980 hcl:#623a2f pid:000000001 xyz:1 abc:2");
+        assert_eq!(p.to_string(), "byr:1980 hcl:#623a2f pid:000000001 abc:2 xyz:1");
+        assert_eq!(p.unknown(), vec![("abc", "2"), ("xyz", "1")]);
+        assert_eq!(p.value("xyz"), None);
+        assert!(!p.get("xyz").unwrap().valid);
+    }
+}
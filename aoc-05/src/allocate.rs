@@ -0,0 +1,223 @@
+use std::fmt;
+
+use crate::layout::PlaneLayout;
+use crate::seat::Seat;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Want {
+    Any,
+    Window,
+    // Next to an aisle.
+    Aisle,
+    Row(usize),
+    // Side by side in one row, with no aisle between them.
+    Group(usize),
+}
+
+impl fmt::Display for Want {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Want::Any => write!(f, "any seat"),
+            Want::Window => write!(f, "a window seat"),
+            Want::Aisle => write!(f, "an aisle seat"),
+            Want::Row(row) => write!(f, "a seat in row {}", row),
+            Want::Group(n) => write!(f, "{} seats together", n),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RequestError {
+    Unknown(String),
+    BadNumber(String),
+    TooLong,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::Unknown(w) => write!(f, "unknown request {:?}, expected window, aisle, row N or group N", w),
+            RequestError::BadNumber(n) => write!(f, "{:?} is not a number", n),
+            RequestError::TooLong => write!(f, "expected a name and at most one request"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub name : String,
+    pub want : Want,
+}
+
+// One request per line: a name, then nothing for any seat, or window,
+// aisle, row N or group N. Blank lines and lines starting with # are not
+// requests.
+pub fn parse(line : &str) -> Option<Result<Request, RequestError>> {
+    let words : Vec<&str> = line.split_whitespace().collect();
+    let name = match words.first() {
+        Some(w) if !w.starts_with('#') => w.to_string(),
+        _ => return None,
+    };
+    let num = |i : usize| -> Result<usize, RequestError> {
+        match words.get(i) {
+            Some(n) => n.parse().map_err(|_| RequestError::BadNumber(n.to_string())),
+            None => Err(RequestError::BadNumber(String::new())),
+        }
+    };
+    let (want, len) = match words.get(1).copied() {
+        None => (Ok(Want::Any), 1),
+        Some("window") => (Ok(Want::Window), 2),
+        Some("aisle") => (Ok(Want::Aisle), 2),
+        Some("row") => (num(2).map(Want::Row), 3),
+        Some("group") => (num(2).map(Want::Group), 3),
+        Some(w) => (Err(RequestError::Unknown(w.to_string())), 2),
+    };
+    Some(want.and_then(|want| {
+        if words.len() > len {
+            Err(RequestError::TooLong)
+        } else {
+            Ok(Request { name, want })
+        }
+    }))
+}
+
+// Seats on a plane, given out front to back and left to right.
+pub struct Allocator<'a> {
+    layout : &'a PlaneLayout,
+    taken : Vec<bool>,
+}
+
+impl<'a> Allocator<'a> {
+    pub fn new(layout : &'a PlaneLayout) -> Allocator<'a> {
+        Allocator { layout, taken: vec![false; layout.rows() * layout.cols()] }
+    }
+
+    // Seats that are already gone, like the ones on earlier passes. IDs
+    // that are not seats are left alone.
+    pub fn occupy(&mut self, ids : &[usize]) {
+        for &id in ids {
+            if self.layout.seat_from_id(id).is_some() {
+                self.taken[id] = true;
+            }
+        }
+    }
+
+    fn free(&self, row : usize, col : usize) -> Option<Seat> {
+        self.layout.seat(row, col).filter(|s| !self.taken[s.id()])
+    }
+
+    fn fits(&self, seat : &Seat, want : Want) -> bool {
+        let col = seat.col();
+        match want {
+            Want::Window => col == 0 || col == self.layout.cols() - 1,
+            Want::Aisle => self.layout.has_aisle_before(col) || self.layout.has_aisle_before(col + 1),
+            Want::Row(row) => seat.row() == row,
+            Want::Any | Want::Group(_) => true,
+        }
+    }
+
+    // The first n free seats side by side, or nothing.
+    fn together(&self, n : usize) -> Option<Vec<Seat>> {
+        if n == 0 || n > self.layout.cols() {
+            return None;
+        }
+        for row in 0..self.layout.rows() {
+            for start in 0..=self.layout.cols() - n {
+                let seats : Vec<Seat> = (start..start + n).filter_map(|col| self.free(row, col)).collect();
+                let split = (start + 1..start + n).any(|col| self.layout.has_aisle_before(col));
+                if seats.len() == n && !split {
+                    return Some(seats);
+                }
+            }
+        }
+        None
+    }
+
+    // The seats given to the request, or None if it cannot be met.
+    pub fn assign(&mut self, want : Want) -> Option<Vec<Seat>> {
+        let seats = match want {
+            Want::Group(n) => self.together(n)?,
+            _ => {
+                let seat = (0..self.taken.len())
+                    .filter_map(|id| self.free(id / self.layout.cols(), id % self.layout.cols()))
+                    .find(|s| self.fits(s, want))?;
+                vec![seat]
+            }
+        };
+        for s in &seats {
+            self.taken[s.id()] = true;
+        }
+        Some(seats)
+    }
+
+    pub fn taken(&self) -> Vec<usize> {
+        (0..self.taken.len()).filter(|&id| self.taken[id]).collect()
+    }
+
+    pub fn seats(&self) -> usize {
+        (0..self.layout.rows()).filter(|&r| !self.layout.is_excluded(r)).count() * self.layout.cols()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(name : &str, want : Want) -> Option<Result<Request, RequestError>> {
+        Some(Ok(Request { name: name.to_string(), want }))
+    }
+
+    fn passes(layout : &PlaneLayout, seats : Option<Vec<Seat>>) -> Vec<String> {
+        seats.unwrap_or_default().iter().map(|s| layout.encode(s)).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("ann"), request("ann", Want::Any));
+        assert_eq!(parse("  bob   window "), request("bob", Want::Window));
+        assert_eq!(parse("cy aisle"), request("cy", Want::Aisle));
+        assert_eq!(parse("dee row 12"), request("dee", Want::Row(12)));
+        assert_eq!(parse("smiths group 4"), request("smiths", Want::Group(4)));
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("# comment"), None);
+        assert_eq!(parse("eve middle"), Some(Err(RequestError::Unknown("middle".to_string()))));
+        assert_eq!(parse("fay row x"), Some(Err(RequestError::BadNumber("x".to_string()))));
+        assert_eq!(parse("fay row"), Some(Err(RequestError::BadNumber(String::new()))));
+        assert_eq!(parse("gus window please"), Some(Err(RequestError::TooLong)));
+    }
+
+    #[test]
+    fn test_assign() {
+        // Two rows of AB CD.
+        let plane = PlaneLayout::new(2, 4).unwrap();
+        let mut a = Allocator::new(&plane);
+        a.occupy(&[0, 99]);
+        assert_eq!(passes(&plane, a.assign(Want::Aisle)), vec!["FLR"]);
+        assert_eq!(passes(&plane, a.assign(Want::Window)), vec!["FRR"]);
+        assert_eq!(passes(&plane, a.assign(Want::Group(2))), vec!["BLL", "BLR"]);
+        assert_eq!(passes(&plane, a.assign(Want::Row(1))), vec!["BRL"]);
+        // Two seats are left, but on different rows.
+        assert_eq!(a.assign(Want::Group(2)), None);
+        assert_eq!(passes(&plane, a.assign(Want::Any)), vec!["FRL"]);
+        assert_eq!(passes(&plane, a.assign(Want::Window)), vec!["BRR"]);
+        assert_eq!(a.assign(Want::Any), None);
+        assert_eq!(a.taken().len(), a.seats());
+    }
+
+    #[test]
+    fn test_unmet() {
+        let plane = PlaneLayout::new(3, 4).unwrap().exclude_rows(&[1]).unwrap();
+        let mut a = Allocator::new(&plane);
+        assert_eq!(a.seats(), 8);
+        assert_eq!(a.assign(Want::Row(1)), None);
+        assert_eq!(a.assign(Want::Row(7)), None);
+        assert_eq!(a.assign(Want::Group(3)), None);
+        assert_eq!(a.assign(Want::Group(0)), None);
+        assert!(a.taken().is_empty());
+        // Without the aisle, four fit in a row.
+        let plane = plane.with_aisles(&[]).unwrap();
+        let mut a = Allocator::new(&plane);
+        assert_eq!(a.assign(Want::Group(4)).map(|s| s.len()), Some(4));
+        assert_eq!(a.assign(Want::Row(2)).map(|s| s[0].id()), Some(8));
+    }
+}
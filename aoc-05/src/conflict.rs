@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// What to do about two passes for the same seat.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnConflict {
+    // Report them and carry on with the first pass for each seat.
+    Warn,
+    // Carry on with the first pass for each seat without a word.
+    Ignore,
+    // Report them and stop.
+    Fail,
+}
+
+impl OnConflict {
+    pub fn parse(s : &str) -> Option<OnConflict> {
+        match s {
+            "warn" => Some(OnConflict::Warn),
+            "ignore" => Some(OnConflict::Ignore),
+            "fail" => Some(OnConflict::Fail),
+            _ => None,
+        }
+    }
+}
+
+// A seat given out twice. Lines count from 1, and first is the line where
+// the seat was first seen, so three passes for a seat make two conflicts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Conflict {
+    pub id : usize,
+    pub first : usize,
+    pub line : usize,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "seat {} is on lines {} and {}", self.id, self.first, self.line)
+    }
+}
+
+// ids are in line order. Returns the IDs with every repeat left out,
+// along with the repeats.
+pub fn find(ids : &[usize]) -> (Vec<usize>, Vec<Conflict>) {
+    let mut seen : HashMap<usize, usize> = HashMap::new();
+    let mut unique = Vec::with_capacity(ids.len());
+    let mut conflicts = Vec::new();
+    for (i, &id) in ids.iter().enumerate() {
+        match seen.get(&id) {
+            Some(&first) => conflicts.push(Conflict { id, first, line: i + 1 }),
+            None => {
+                seen.insert(id, i + 1);
+                unique.push(id);
+            }
+        }
+    }
+    (unique, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_conflicts() {
+        assert_eq!(find(&[357, 567, 119]), (vec![357, 567, 119], vec![]));
+        assert_eq!(find(&[]), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let (unique, conflicts) = find(&[5, 7, 5, 9, 7, 5]);
+        assert_eq!(unique, vec![5, 7, 9]);
+        assert_eq!(conflicts, vec![
+            Conflict { id: 5, first: 1, line: 3 },
+            Conflict { id: 7, first: 2, line: 5 },
+            Conflict { id: 5, first: 1, line: 6 },
+        ]);
+        assert_eq!(conflicts[0].to_string(), "seat 5 is on lines 1 and 3");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(OnConflict::parse("fail"), Some(OnConflict::Fail));
+        assert_eq!(OnConflict::parse("ignore"), Some(OnConflict::Ignore));
+        assert_eq!(OnConflict::parse("Warn"), None);
+    }
+}
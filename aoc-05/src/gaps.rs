@@ -0,0 +1,136 @@
+use std::fmt;
+
+use crate::layout::PlaneLayout;
+
+// Consecutive free seat IDs, first to last inclusive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Run {
+    pub first : usize,
+    pub last : usize,
+}
+
+impl Run {
+    pub fn len(&self) -> usize {
+        self.last - self.first + 1
+    }
+}
+
+impl fmt::Display for Run {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first)
+        } else {
+            write!(f, "{}-{}", self.first, self.last)
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Gaps {
+    // In ID order.
+    pub missing : Vec<usize>,
+    pub runs : Vec<Run>,
+    lonely : Vec<usize>,
+}
+
+impl Gaps {
+    pub fn is_full(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    // Free seats with a taken seat on both sides, like the puzzle's own. A
+    // free seat next to a row with no seats is not one of them.
+    pub fn lonely(&self) -> impl Iterator<Item = usize> + '_ {
+        self.lonely.iter().copied()
+    }
+}
+
+// Every ID between the lowest and highest taken ones that nobody has. With
+// a layout, IDs that are not seats on it are neither free nor part of a run.
+// ids may be in any order and have repeats.
+pub fn find(ids : &[usize], layout : Option<&PlaneLayout>) -> Gaps {
+    let (min, max) = match (ids.iter().min(), ids.iter().max()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => return Gaps::default(),
+    };
+    let mut taken = vec![false; max - min + 1];
+    for &id in ids {
+        taken[id - min] = true;
+    }
+
+    let mut gaps = Gaps::default();
+    let mut run : Option<Run> = None;
+    for id in min..=max {
+        let free = !taken[id - min] && layout.map_or(true, |l| l.seat_from_id(id).is_some());
+        if free {
+            gaps.missing.push(id);
+            run = Some(run.map_or(Run { first: id, last: id }, |r| Run { last: id, ..r }));
+        } else if let Some(r) = run.take() {
+            gaps.runs.push(r);
+        }
+    }
+    gaps.runs.extend(run);
+    // min and max are taken, so every run has an ID on both sides.
+    gaps.lonely = gaps.runs.iter()
+        .filter(|r| r.len() == 1 && taken[r.first - 1 - min] && taken[r.first + 1 - min])
+        .map(|r| r.first)
+        .collect();
+    gaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(first : usize, last : usize) -> Run {
+        Run { first, last }
+    }
+
+    #[test]
+    fn test_single_gap() {
+        let g = find(&[5, 3, 7, 4], None);
+        assert_eq!(g.missing, vec![6]);
+        assert_eq!(g.runs, vec![run(6, 6)]);
+        assert_eq!(g.lonely().collect::<Vec<_>>(), vec![6]);
+    }
+
+    #[test]
+    fn test_runs() {
+        let g = find(&[10, 11, 15, 17, 17, 20], None);
+        assert_eq!(g.missing, vec![12, 13, 14, 16, 18, 19]);
+        assert_eq!(g.runs, vec![run(12, 14), run(16, 16), run(18, 19)]);
+        assert_eq!(g.lonely().collect::<Vec<_>>(), vec![16]);
+        let shown : Vec<String> = g.runs.iter().map(|r| r.to_string()).collect();
+        assert_eq!(shown, vec!["12-14", "16", "18-19"]);
+    }
+
+    #[test]
+    fn test_no_free_seat() {
+        // The panic case: nothing missing, so no next element to look at.
+        for ids in &[vec![], vec![42], vec![1, 2, 3], vec![3, 2, 2, 1]] {
+            let g = find(ids, None);
+            assert!(g.is_full());
+            assert!(g.runs.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let plane = PlaneLayout::default().exclude_rows(&[2]).unwrap();
+        // Row 1 ends at 15, row 2 is 16-23 and row 3 starts at 24.
+        let g = find(&[14, 25], Some(&plane));
+        assert_eq!(g.missing, vec![15, 24]);
+        assert_eq!(g.runs, vec![run(15, 15), run(24, 24)]);
+        // Each is next to the row with no seats, not between two passengers.
+        assert_eq!(g.lonely().count(), 0);
+        let g = find(&[12, 14, 25], Some(&plane));
+        assert_eq!(g.runs, vec![run(13, 13), run(15, 15), run(24, 24)]);
+        assert_eq!(g.lonely().collect::<Vec<_>>(), vec![13]);
+        let g = find(&[14, 25], None);
+        assert_eq!(g.runs, vec![run(15, 24)]);
+        assert_eq!(g.missing.len(), 10);
+
+        let g = find(&[15, 24], Some(&plane));
+        assert!(g.is_full());
+    }
+}
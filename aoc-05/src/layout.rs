@@ -0,0 +1,239 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::seat::{PassError, Seat};
+
+// How many letters it takes to tell n things apart.
+fn bits(n : usize) -> usize {
+    (usize::BITS - n.saturating_sub(1).leading_zeros()) as usize
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayoutError {
+    Empty,
+    // Every letter has to be different, or passes cannot be decoded.
+    Letters([char; 4]),
+    ExcludedRow(usize),
+    Aisle(usize),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::Empty => write!(f, "a plane needs at least one row and one column"),
+            LayoutError::Letters(l) => write!(f, "letters {:?} are not all different", l.iter().collect::<String>()),
+            LayoutError::ExcludedRow(row) => write!(f, "excluded row {} is not on the plane", row),
+            LayoutError::Aisle(col) => write!(f, "an aisle before column {} is not between two seats", col),
+        }
+    }
+}
+
+// The seats of a plane and how passes name them. Passes use just enough
+// letters for the row and then for the column, so rows and columns need not
+// be powers of two, but then some passes are not seats.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaneLayout {
+    rows : usize,
+    cols : usize,
+    // The letters for a 0 and a 1 bit.
+    row_letters : [char; 2],
+    col_letters : [char; 2],
+    // Rows with no seats, like galleys and exits.
+    excluded : BTreeSet<usize>,
+    // Columns with an aisle just before them. Only for drawing.
+    aisles : BTreeSet<usize>,
+}
+
+// The plane from the puzzle.
+impl Default for PlaneLayout {
+    fn default() -> PlaneLayout {
+        PlaneLayout {
+            rows: 128,
+            cols: 8,
+            row_letters: ['F', 'B'],
+            col_letters: ['L', 'R'],
+            excluded: BTreeSet::new(),
+            aisles: [4].iter().copied().collect(),
+        }
+    }
+}
+
+impl PlaneLayout {
+    // Planes with four or more columns get an aisle down the middle.
+    pub fn new(rows : usize, cols : usize) -> Result<PlaneLayout, LayoutError> {
+        if rows == 0 || cols == 0 {
+            return Err(LayoutError::Empty);
+        }
+        let aisles = if cols >= 4 { [cols / 2].iter().copied().collect() } else { BTreeSet::new() };
+        Ok(PlaneLayout { rows, cols, aisles, ..PlaneLayout::default() })
+    }
+
+    pub fn with_letters(mut self, row : [char; 2], col : [char; 2]) -> Result<PlaneLayout, LayoutError> {
+        let all = [row[0], row[1], col[0], col[1]];
+        if (1..4).any(|i| all[..i].contains(&all[i])) {
+            return Err(LayoutError::Letters(all));
+        }
+        self.row_letters = row;
+        self.col_letters = col;
+        Ok(self)
+    }
+
+    pub fn exclude_rows(mut self, rows : &[usize]) -> Result<PlaneLayout, LayoutError> {
+        if let Some(&row) = rows.iter().find(|&&r| r >= self.rows) {
+            return Err(LayoutError::ExcludedRow(row));
+        }
+        self.excluded.extend(rows);
+        Ok(self)
+    }
+
+    pub fn with_aisles(mut self, before : &[usize]) -> Result<PlaneLayout, LayoutError> {
+        if let Some(&col) = before.iter().find(|&&c| c == 0 || c >= self.cols) {
+            return Err(LayoutError::Aisle(col));
+        }
+        self.aisles = before.iter().copied().collect();
+        Ok(self)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn is_excluded(&self, row : usize) -> bool {
+        self.excluded.contains(&row)
+    }
+
+    pub fn has_aisle_before(&self, col : usize) -> bool {
+        self.aisles.contains(&col)
+    }
+
+    pub fn pass_len(&self) -> usize {
+        bits(self.rows) + bits(self.cols)
+    }
+
+    pub fn seat(&self, row : usize, col : usize) -> Option<Seat> {
+        Seat::new_in(self, row, col)
+    }
+
+    pub fn seat_from_id(&self, id : usize) -> Option<Seat> {
+        self.seat(id / self.cols, id % self.cols)
+    }
+
+    // Every seat, in ID order.
+    #[cfg(test)]
+    pub fn seats(&self) -> impl Iterator<Item = Seat> + '_ {
+        (0..self.rows * self.cols).filter_map(move |id| self.seat_from_id(id))
+    }
+
+    pub fn decode(&self, pass : &str) -> Result<Seat, PassError> {
+        let row_bits = bits(self.rows);
+        let expected = self.pass_len();
+        let found = pass.chars().count();
+        if found != expected {
+            return Err(PassError::Length { expected, found });
+        }
+        let (mut row, mut col) = (0, 0);
+        for (index, c) in pass.chars().enumerate() {
+            let (n, letters) = if index < row_bits { (&mut row, self.row_letters) } else { (&mut col, self.col_letters) };
+            match letters.iter().position(|&l| l == c) {
+                Some(bit) => *n = *n << 1 | bit,
+                None if index < row_bits => return Err(PassError::Row { index, c, expected: letters }),
+                None => return Err(PassError::Col { index, c, expected: letters }),
+            }
+        }
+        if row >= self.rows || col >= self.cols {
+            return Err(PassError::OutOfBounds { row, col });
+        }
+        if self.is_excluded(row) {
+            return Err(PassError::ExcludedRow { row });
+        }
+        Ok(self.seat(row, col).unwrap())
+    }
+
+    pub fn encode(&self, seat : &Seat) -> String {
+        let letters = |n : usize, len : usize, l : [char; 2]| -> String {
+            (0..len).rev().map(|i| l[n >> i & 1]).collect()
+        };
+        format!("{}{}", letters(seat.row(), bits(self.rows), self.row_letters),
+                letters(seat.col(), bits(self.cols), self.col_letters))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bits() {
+        assert_eq!(bits(1), 0);
+        assert_eq!(bits(2), 1);
+        assert_eq!(bits(8), 3);
+        assert_eq!(bits(9), 4);
+        assert_eq!(bits(128), 7);
+        assert_eq!(PlaneLayout::default().pass_len(), 10);
+    }
+
+    #[test]
+    fn test_odd_sizes() {
+        // 30 rows of A-F, like a narrow-body jet.
+        let plane = PlaneLayout::new(30, 6).unwrap().with_letters(['F', 'B'], ['L', 'R']).unwrap();
+        assert_eq!(plane.pass_len(), 8);
+        assert_eq!(plane.seats().count(), 180);
+        for seat in plane.seats() {
+            assert_eq!(plane.decode(&plane.encode(&seat)), Ok(seat));
+            assert_eq!(plane.seat_from_id(seat.id()), Some(seat));
+        }
+        assert_eq!(plane.decode("BBBBFLLL"), Err(PassError::OutOfBounds { row: 30, col: 0 }));
+        assert_eq!(plane.decode("FFFFFRRL"), Err(PassError::OutOfBounds { row: 0, col: 6 }));
+        assert!(plane.seat(29, 5).is_some());
+        assert!(plane.seat(30, 0).is_none());
+    }
+
+    #[test]
+    fn test_excluded_rows() {
+        let plane = PlaneLayout::default().exclude_rows(&[0, 1, 60]).unwrap();
+        assert_eq!(plane.seats().count(), 125 * 8);
+        assert!(plane.seat(60, 3).is_none());
+        assert!(plane.seat_from_id(7).is_none());
+        assert_eq!(plane.decode("FFFFFFBLLL"), Err(PassError::ExcludedRow { row: 1 }));
+        assert_eq!(plane.decode("FFFFFBFLLL").map(|s| s.id()), Ok(16));
+        assert_eq!(PlaneLayout::default().exclude_rows(&[128]), Err(LayoutError::ExcludedRow(128)));
+    }
+
+    #[test]
+    fn test_letters() {
+        let plane = PlaneLayout::new(4, 4).unwrap().with_letters(['0', '1'], ['a', 'b']).unwrap();
+        let seat = plane.seat(2, 1).unwrap();
+        assert_eq!(plane.encode(&seat), "10ab");
+        assert_eq!(plane.decode("10ab"), Ok(seat));
+        assert_eq!(plane.decode("10aB"), Err(PassError::Col { index: 3, c: 'B', expected: ['a', 'b'] }));
+        assert_eq!(plane.decode("10a"), Err(PassError::Length { expected: 4, found: 3 }));
+        assert!(PlaneLayout::default().with_letters(['F', 'B'], ['B', 'R']).is_err());
+        assert!(PlaneLayout::default().with_letters(['F', 'F'], ['L', 'R']).is_err());
+        assert_eq!(PlaneLayout::new(0, 8), Err(LayoutError::Empty));
+    }
+
+    #[test]
+    fn test_aisles() {
+        assert!(PlaneLayout::default().has_aisle_before(4));
+        assert_eq!(PlaneLayout::new(128, 8).unwrap(), PlaneLayout::default());
+        assert!(!PlaneLayout::new(1, 3).unwrap().has_aisle_before(1));
+        let plane = PlaneLayout::new(30, 6).unwrap();
+        assert!(plane.has_aisle_before(3));
+        let plane = plane.with_aisles(&[2, 4]).unwrap();
+        assert!(plane.has_aisle_before(2) && plane.has_aisle_before(4) && !plane.has_aisle_before(3));
+        assert_eq!(plane.clone().with_aisles(&[0]), Err(LayoutError::Aisle(0)));
+        assert_eq!(plane.with_aisles(&[6]), Err(LayoutError::Aisle(6)));
+    }
+
+    #[test]
+    fn test_single_row() {
+        let plane = PlaneLayout::new(1, 4).unwrap();
+        assert_eq!(plane.pass_len(), 2);
+        assert_eq!(plane.decode("RL").map(|s| s.id()), Ok(2));
+        assert_eq!(plane.encode(&plane.seat(0, 3).unwrap()), "RR");
+    }
+}
@@ -1,18 +1,22 @@
+use std::env;
+use std::fs::{self, File};
 use std::io;
+use std::process;
 use std::io::prelude::*;
 
+mod allocate;
+mod conflict;
+mod gaps;
+mod layout;
+mod render;
+mod seat;
 
-fn get_id(ticket: &str) -> usize {
-    let mut res : usize = 0;
-    //let len = ticket.len();
-    for (i, c) in ticket.chars().rev().enumerate() {
-        if c == 'B' || c == 'R' {
-            res += 2_usize.pow(i as u32);
-        }
-        //println!("{}: {} -> {}", i, c, res);
-    }
-    //println!("{}: {}", ticket, res);
-    return res;
+use conflict::OnConflict;
+use layout::PlaneLayout;
+use seat::PassError;
+
+fn get_id(layout: &PlaneLayout, ticket: &str) -> Result<usize, PassError> {
+    layout.decode(ticket).map(|s| s.id())
 }
 
 fn get_input() -> Vec<String> {
@@ -20,49 +24,235 @@ fn get_input() -> Vec<String> {
 
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
-        let l = line.unwrap();
-        if l.len() == 0 {
+        let l = line.unwrap().trim_end_matches('\r').to_string();
+        if l.is_empty() {
             break;
         }
         res.push(l);
     }
 
-    return res;
+    res
 }
 
-fn part1() {
+// Stops at the first bad pass, there is no telling which seat it was. Each
+// seat is in the result once, however many passes there are for it.
+fn get_ids(layout: &PlaneLayout, tickets: &[String], on_conflict: OnConflict) -> Vec<usize> {
+    let mut ids = Vec::new();
+    for (i, t) in tickets.iter().enumerate() {
+        match get_id(layout, t) {
+            Ok(id) => ids.push(id),
+            Err(e) => {
+                eprintln!("Bad pass {:?} on line {}: {}", t, i + 1, e);
+                process::exit(1);
+            }
+        }
+    }
+    let (ids, conflicts) = conflict::find(&ids);
+    if on_conflict != OnConflict::Ignore {
+        for c in &conflicts {
+            eprintln!("Duplicate pass {:?}: {}", tickets[c.line - 1], c);
+        }
+    }
+    if on_conflict == OnConflict::Fail && !conflicts.is_empty() {
+        process::exit(1);
+    }
+    ids
+}
+
+fn part1(layout: &PlaneLayout, on_conflict: OnConflict) {
     let tickets = get_input();
-    let mut max = 0;
+    // None until a pass is read, so no seat is made up for empty input.
+    let max = get_ids(layout, &tickets, on_conflict).into_iter().max();
+
+    match max.map(|id| (id, layout.seat_from_id(id))) {
+        Some((id, Some(s))) => println!("Max: {} (row {}, column {}, pass {})", id, s.row(), s.col(), layout.encode(&s)),
+        Some((id, None)) => println!("Max: {}", id),
+        None => println!("Max: 0"),
+    }
+}
 
-    for t in tickets {
-        let res = get_id(&t);
-        if res > max {
-            max = res;
+// Free seats are only looked for between the first and last taken ones,
+// and only on the plane unless --ignore-layout is given.
+fn part2(layout: &PlaneLayout, on_conflict: OnConflict, args: &[String]) {
+    let tickets = get_input();
+    let ids = get_ids(layout, &tickets, on_conflict);
+    let on_plane = if args.iter().any(|a| a == "--ignore-layout") { None } else { Some(layout) };
+    let gaps = gaps::find(&ids, on_plane);
+    if gaps.is_full() {
+        println!("No free seat");
+        return;
+    }
+    for id in gaps.lonely() {
+        match layout.seat_from_id(id) {
+            Some(s) => println!("Lonely seat: {} (pass {})", id, layout.encode(&s)),
+            None => println!("Lonely seat: {}", id),
         }
     }
-    println!("Max: {}", max);
+    let runs : Vec<String> = gaps.runs.iter().map(|r| r.to_string()).collect();
+    println!("Free seats: {} ({})", gaps.missing.len(), runs.join(", "));
 }
 
-fn part2() {
+// render [--mine PASS] [--color] [--svg FILE]
+// Our own seat is the one on --mine, or else the first lonely free seat.
+fn draw(layout: &PlaneLayout, on_conflict: OnConflict, args: &[String]) {
     let tickets = get_input();
-    let mut ids : Vec<usize> = Vec::new();
+    let ids = get_ids(layout, &tickets, on_conflict);
+    let value = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1));
+    let mine = match value("--mine") {
+        Some(pass) => match get_id(layout, pass) {
+            Ok(id) => Some(id),
+            Err(e) => {
+                eprintln!("--mine: bad pass {:?}: {}", pass, e);
+                process::exit(1);
+            }
+        },
+        None => gaps::find(&ids, Some(layout)).lonely().next(),
+    };
+    let cells = render::cells(layout, &ids, mine);
+    match value("--svg") {
+        Some(path) => {
+            let res = File::create(path)
+                .and_then(|f| render::write_svg(layout, &cells, io::BufWriter::new(f)));
+            if let Err(e) = res {
+                eprintln!("Cannot write {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", render::text(layout, &cells, args.iter().any(|a| a == "--color"))),
+    }
+}
 
-    for t in tickets {
-        ids.push(get_id(&t));
+// simulate REQUESTS [--map]
+// Seats the passengers in the REQUESTS file, one per line, around the passes
+// already given out, and prints their passes.
+fn simulate(layout: &PlaneLayout, on_conflict: OnConflict, args: &[String]) {
+    let path = match args.first() {
+        Some(p) if !p.starts_with("--") => p,
+        _ => {
+            eprintln!("simulate: expected a file of seat requests");
+            process::exit(1);
+        }
+    };
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Cannot read {}: {}", path, e);
+        process::exit(1);
+    });
+    let mut requests = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        match allocate::parse(line) {
+            Some(Ok(r)) => requests.push(r),
+            Some(Err(e)) => {
+                eprintln!("Bad request {:?} on line {}: {}", line, i + 1, e);
+                process::exit(1);
+            }
+            None => {}
+        }
     }
-    ids.sort();
-    for (i, id) in ids.iter().enumerate() {
-        if (ids[i+1] - id) == 2 {
-            println!("Lonely seat: {}", id+1);
-            break;
+
+    let tickets = get_input();
+    let mut seats = allocate::Allocator::new(layout);
+    seats.occupy(&get_ids(layout, &tickets, on_conflict));
+    let mut unmet = Vec::new();
+    for r in &requests {
+        match seats.assign(r.want) {
+            Some(got) => {
+                let passes : Vec<String> = got.iter().map(|s| layout.encode(s)).collect();
+                println!("{}: {}", r.name, passes.join(" "));
+            }
+            None => unmet.push(r),
         }
     }
+    for r in &unmet {
+        println!("Unmet: {} wanted {}", r.name, r.want);
+    }
+    let taken = seats.taken();
+    println!("Occupancy: {}/{} seats ({:.1}%)", taken.len(), seats.seats(),
+             100.0 * taken.len() as f64 / seats.seats() as f64);
+    if args.iter().any(|a| a == "--map") {
+        print!("{}", render::text(layout, &render::cells(layout, &taken, None), false));
+    }
+}
+
+fn parse_num(args: &[String], flag: &str) -> Option<usize> {
+    let i = args.iter().position(|a| a == flag)?;
+    match args.get(i + 1).map(|s| s.parse()) {
+        Some(Ok(n)) => Some(n),
+        _ => {
+            eprintln!("{}: expected a number", flag);
+            process::exit(1);
+        }
+    }
+}
+
+// --rows and --cols size the plane, --letters gives the row and column
+// letters as four characters like FBLR, --exclude lists rows with no seats
+// and --aisles the columns with an aisle before them, separated by commas.
+fn load_layout(args: &[String]) -> PlaneLayout {
+    let default = PlaneLayout::default();
+    let rows = parse_num(args, "--rows").unwrap_or(default.rows());
+    let cols = parse_num(args, "--cols").unwrap_or(default.cols());
+    let value = |flag: &str| args.iter().position(|a| a == flag).map(|i| args.get(i + 1).map_or("", |s| s.as_str()));
+    let letters : Vec<char> = value("--letters").unwrap_or("FBLR").chars().collect();
+    if letters.len() != 4 {
+        eprintln!("--letters: expected four letters, like FBLR");
+        process::exit(1);
+    }
+    let list = |flag: &str| -> Vec<usize> {
+        let mut res = Vec::new();
+        for n in value(flag).unwrap_or("").split(',').filter(|n| !n.is_empty()) {
+            match n.trim().parse() {
+                Ok(n) => res.push(n),
+                Err(_) => {
+                    eprintln!("{}: {:?} is not a number", flag, n);
+                    process::exit(1);
+                }
+            }
+        }
+        res
+    };
+    let excluded = list("--exclude");
+    let mut layout = PlaneLayout::new(rows, cols)
+        .and_then(|l| l.with_letters([letters[0], letters[1]], [letters[2], letters[3]]))
+        .and_then(|l| l.exclude_rows(&excluded));
+    if value("--aisles").is_some() {
+        let aisles = list("--aisles");
+        layout = layout.and_then(|l| l.with_aisles(&aisles));
+    }
+    match layout {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("Bad plane layout: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+// --on-conflict warn, ignore or fail, for two passes with the same seat.
+// Warn is the default.
+fn load_on_conflict(args: &[String]) -> OnConflict {
+    match args.iter().position(|a| a == "--on-conflict") {
+        None => OnConflict::Warn,
+        Some(i) => match args.get(i + 1).and_then(|s| OnConflict::parse(s)) {
+            Some(o) => o,
+            None => {
+                eprintln!("--on-conflict: expected warn, ignore or fail");
+                process::exit(1);
+            }
+        },
+    }
 }
 
 fn main() {
+    let args : Vec<String> = env::args().skip(1).collect();
+    let layout = load_layout(&args);
+    let on_conflict = load_on_conflict(&args);
     println!("Enter tickets...");
-    //part1();
-    part2();
+    match args.first().map(|a| a.as_str()) {
+        Some("1") => part1(&layout, on_conflict),
+        Some("render") => draw(&layout, on_conflict, &args[1..]),
+        Some("simulate") => simulate(&layout, on_conflict, &args[1..]),
+        _ => part2(&layout, on_conflict, &args),
+    }
 }
 
 #[cfg(test)]
@@ -71,10 +261,17 @@ mod tests {
 
     #[test]
     fn test_row() {
-        assert_eq!(get_id("FBFBBFFRLR"), 357);
-        assert_eq!(get_id("BFFFBBFRRR"), 567);
-        assert_eq!(get_id("FFFBBBFRRR"), 119);
-        assert_eq!(get_id("BBFFBBFRLL"), 820);
+        assert_eq!(get_id(&PlaneLayout::default(), "FBFBBFFRLR"), Ok(357));
+        assert_eq!(get_id(&PlaneLayout::default(), "BFFFBBFRRR"), Ok(567));
+        assert_eq!(get_id(&PlaneLayout::default(), "FFFBBBFRRR"), Ok(119));
+        assert_eq!(get_id(&PlaneLayout::default(), "BBFFBBFRLL"), Ok(820));
+    }
+
+    #[test]
+    fn test_rejects_bad_passes() {
+        assert_eq!(get_id(&PlaneLayout::default(), "XXXXXXXXXX"), Err(PassError::Row { index: 0, c: 'X', expected: ['F', 'B'] }));
+        assert_eq!(get_id(&PlaneLayout::default(), "FBR"), Err(PassError::Length { expected: 10, found: 3 }));
+        assert_eq!(get_id(&PlaneLayout::default(), "RLFBFBBFFB"), Err(PassError::Row { index: 0, c: 'R', expected: ['F', 'B'] }));
     }
 }
 
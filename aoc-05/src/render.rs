@@ -0,0 +1,220 @@
+use std::io;
+use std::io::prelude::*;
+
+use crate::layout::PlaneLayout;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cell {
+    Taken,
+    Free,
+    // Our own seat, free or not.
+    Mine,
+    // Part of a row with no seats.
+    NoSeat,
+}
+
+impl Cell {
+    fn symbol(self) -> char {
+        match self {
+            Cell::Taken => '#',
+            Cell::Free => '.',
+            Cell::Mine => '@',
+            Cell::NoSeat => ' ',
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            Cell::Taken => "\x1b[31m",
+            Cell::Free => "\x1b[2m",
+            Cell::Mine => "\x1b[1;32m",
+            Cell::NoSeat => "",
+        }
+    }
+
+    fn rgb(self) -> &'static str {
+        match self {
+            Cell::Taken => "#c83232",
+            Cell::Free => "#e6e6e6",
+            Cell::Mine => "#28a03c",
+            Cell::NoSeat => "none",
+        }
+    }
+}
+
+// Column names as printed on the plane, A for the first one, then on to Z,
+// AA, AB and so on like a spreadsheet.
+fn col_name(col : usize) -> String {
+    let mut name = Vec::new();
+    let mut n = col + 1;
+    while n > 0 {
+        n -= 1;
+        name.push(b'A' + (n % 26) as u8);
+        n /= 26;
+    }
+    name.iter().rev().map(|&b| b as char).collect()
+}
+
+// One row of cells per plane row. ids may be in any order and have repeats,
+// and those that are not seats on the plane are left out.
+pub fn cells(layout : &PlaneLayout, ids : &[usize], mine : Option<usize>) -> Vec<Vec<Cell>> {
+    let mut grid = vec![vec![Cell::Free; layout.cols()]; layout.rows()];
+    for (row, cells) in grid.iter_mut().enumerate() {
+        if layout.is_excluded(row) {
+            cells.iter_mut().for_each(|c| *c = Cell::NoSeat);
+        }
+    }
+    for &id in ids {
+        if let Some(s) = layout.seat_from_id(id) {
+            grid[s.row()][s.col()] = Cell::Taken;
+        }
+    }
+    if let Some(s) = mine.and_then(|id| layout.seat_from_id(id)) {
+        grid[s.row()][s.col()] = Cell::Mine;
+    }
+    grid
+}
+
+// Row numbers down the left, column letters across the top and a blank
+// column for each aisle. Names longer than one letter go down the header,
+// one letter per line. With colour, each cell resets what it set.
+pub fn text(layout : &PlaneLayout, cells : &[Vec<Cell>], colour : bool) -> String {
+    let width = layout.rows().saturating_sub(1).to_string().len();
+    let names : Vec<String> = (0..layout.cols()).map(col_name).collect();
+    let depth = names.iter().map(|n| n.len()).max().unwrap_or(1);
+    let mut res = String::new();
+    for line in 0..depth {
+        res.push_str(&" ".repeat(width + 1));
+        for (col, name) in names.iter().enumerate() {
+            if layout.has_aisle_before(col) {
+                res.push(' ');
+            }
+            // Right-aligned, so the last letters are all on the last line.
+            res.push(name.chars().rev().nth(depth - 1 - line).unwrap_or(' '));
+        }
+        res.truncate(res.trim_end_matches(' ').len());
+        res.push('\n');
+    }
+    for (row, cells) in cells.iter().enumerate() {
+        res.push_str(&format!("{:>w$} ", row, w = width));
+        for (col, c) in cells.iter().enumerate() {
+            if layout.has_aisle_before(col) {
+                res.push(' ');
+            }
+            if colour && !c.ansi().is_empty() {
+                res.push_str(&format!("{}{}\x1b[0m", c.ansi(), c.symbol()));
+            } else {
+                res.push(c.symbol());
+            }
+        }
+        res.truncate(res.trim_end_matches(' ').len());
+        res.push('\n');
+    }
+    res
+}
+
+const SEAT : usize = 20;
+const GAP : usize = 4;
+const MARGIN : usize = 30;
+
+// Each seat a rounded square, with a seat's width left for each aisle.
+pub fn write_svg<W : Write>(layout : &PlaneLayout, cells : &[Vec<Cell>], mut out : W) -> io::Result<()> {
+    let x = |col : usize| {
+        MARGIN + col * (SEAT + GAP) + (0..=col).filter(|&c| layout.has_aisle_before(c)).count() * SEAT
+    };
+    let y = |row : usize| MARGIN + row * (SEAT + GAP);
+    // A margin past the last seat, wherever x() and y() put it.
+    let width = x(layout.cols() - 1) + SEAT + MARGIN;
+    let height = y(layout.rows() - 1) + SEAT + MARGIN;
+    writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+             width, height)?;
+    for col in 0..layout.cols() {
+        writeln!(out, r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#, x(col) + SEAT / 2, MARGIN - 8, col_name(col))?;
+    }
+    for (row, cells) in cells.iter().enumerate() {
+        writeln!(out, r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#, MARGIN - 6, y(row) + 15, row)?;
+        for (col, c) in cells.iter().enumerate().filter(|(_, &c)| c != Cell::NoSeat) {
+            writeln!(out, r#"<rect x="{}" y="{}" width="{}" height="{}" rx="4" fill="{}"/>"#,
+                     x(col), y(row), SEAT, SEAT, c.rgb())?;
+        }
+    }
+    writeln!(out, "</svg>")?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells() {
+        let plane = PlaneLayout::new(3, 2).unwrap().exclude_rows(&[1]).unwrap();
+        let grid = cells(&plane, &[0, 5, 5, 2, 99], Some(4));
+        assert_eq!(grid, vec![
+            vec![Cell::Taken, Cell::Free],
+            vec![Cell::NoSeat, Cell::NoSeat],
+            vec![Cell::Mine, Cell::Taken],
+        ]);
+    }
+
+    #[test]
+    fn test_text() {
+        let plane = PlaneLayout::new(12, 4).unwrap().exclude_rows(&[1]).unwrap();
+        let grid = cells(&plane, &[0, 1, 3, 47], Some(2));
+        let map = text(&plane, &grid, false);
+        let lines : Vec<&str> = map.lines().collect();
+        assert_eq!(lines[0], "   AB CD");
+        assert_eq!(lines[1], " 0 ## @#");
+        assert_eq!(lines[2], " 1");
+        assert_eq!(lines[3], " 2 .. ..");
+        assert_eq!(lines[12], "11 .. .#");
+        assert_eq!(lines.len(), 13);
+    }
+
+    #[test]
+    fn test_text_colour() {
+        let plane = PlaneLayout::new(1, 1).unwrap();
+        let map = text(&plane, &cells(&plane, &[], Some(0)), true);
+        assert_eq!(map, "  A\n0 \x1b[1;32m@\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_text_colour_mixed() {
+        // Bold from our seat must not carry over to the dim free seat, or
+        // red to the row with no seats.
+        let plane = PlaneLayout::new(2, 3).unwrap().exclude_rows(&[1]).unwrap();
+        let map = text(&plane, &cells(&plane, &[0], Some(1)), true);
+        let lines : Vec<&str> = map.lines().collect();
+        assert_eq!(lines[1], "0 \x1b[31m#\x1b[0m\x1b[1;32m@\x1b[0m\x1b[2m.\x1b[0m");
+        assert_eq!(lines[2], "1");
+    }
+
+    #[test]
+    fn test_col_names() {
+        let names : Vec<String> = [0, 1, 25, 26, 27, 51, 52, 701, 702].iter().map(|&c| col_name(c)).collect();
+        assert_eq!(names, vec!["A", "B", "Z", "AA", "AB", "AZ", "BA", "ZZ", "AAA"]);
+        let plane = PlaneLayout::new(1, 28).unwrap().with_aisles(&[]).unwrap();
+        let map = text(&plane, &cells(&plane, &[], None), false);
+        let lines : Vec<&str> = map.lines().collect();
+        assert_eq!(lines[0], format!("  {}AA", " ".repeat(26)));
+        assert_eq!(lines[1], "  ABCDEFGHIJKLMNOPQRSTUVWXYZAB");
+        assert_eq!(lines[2], format!("0 {}", ".".repeat(28)));
+    }
+
+    #[test]
+    fn test_svg() {
+        let plane = PlaneLayout::new(2, 4).unwrap().exclude_rows(&[1]).unwrap();
+        let mut out = Vec::new();
+        write_svg(&plane, &cells(&plane, &[1], Some(2)), &mut out).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<rect ").count(), 4);
+        assert_eq!(svg.matches(Cell::Taken.rgb()).count(), 1);
+        assert_eq!(svg.matches(Cell::Mine.rgb()).count(), 1);
+        // Column C is past the aisle, a seat's width further on.
+        assert!(svg.contains(r#"<rect x="98" y="30""#), "{}", svg);
+        // Column D ends at 98 + 24 + 20, then the margin.
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="172" height="104""#), "{}", svg);
+    }
+}
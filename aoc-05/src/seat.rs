@@ -0,0 +1,126 @@
+use std::fmt;
+
+use crate::layout::PlaneLayout;
+
+// Why a string is not a boarding pass. index counts characters from 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PassError {
+    Length { expected : usize, found : usize },
+    // Not one of the row letters in the row part.
+    Row { index : usize, c : char, expected : [char; 2] },
+    // Not one of the column letters in the column part.
+    Col { index : usize, c : char, expected : [char; 2] },
+    OutOfBounds { row : usize, col : usize },
+    ExcludedRow { row : usize },
+}
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PassError::Length { expected, found } => write!(f, "expected {} characters, found {}", expected, found),
+            PassError::Row { index, c, expected: [a, b] } | PassError::Col { index, c, expected: [a, b] } => {
+                write!(f, "character {}: expected {} or {}, found {:?}", index, a, b, c)
+            }
+            PassError::OutOfBounds { row, col } => write!(f, "row {}, column {} is not on the plane", row, col),
+            PassError::ExcludedRow { row } => write!(f, "row {} has no seats", row),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Seat {
+    row : usize,
+    col : usize,
+    // Columns on the plane, for the ID.
+    cols : usize,
+}
+
+impl Seat {
+    pub fn new_in(layout : &PlaneLayout, row : usize, col : usize) -> Option<Seat> {
+        if row < layout.rows() && col < layout.cols() && !layout.is_excluded(row) {
+            Some(Seat { row, col, cols: layout.cols() })
+        } else {
+            None
+        }
+    }
+
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    pub fn id(&self) -> usize {
+        self.row * self.cols + self.col
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FB : [char; 2] = ['F', 'B'];
+    const LR : [char; 2] = ['L', 'R'];
+
+    fn seat(row : usize, col : usize) -> Option<Seat> {
+        PlaneLayout::default().seat(row, col)
+    }
+
+    fn decode(pass : &str) -> Result<Seat, PassError> {
+        PlaneLayout::default().decode(pass)
+    }
+
+    fn encode(seat : &Seat) -> String {
+        PlaneLayout::default().encode(seat)
+    }
+
+    #[test]
+    fn test_examples() {
+        let s = decode("FBFBBFFRLR").unwrap();
+        assert_eq!((s.row(), s.col(), s.id()), (44, 5, 357));
+        let s = decode("BBFFBBFRLL").unwrap();
+        assert_eq!((s.row(), s.col(), s.id()), (102, 4, 820));
+        assert_eq!(encode(&seat(70, 7).unwrap()), "BFFFBBFRRR");
+        assert_eq!(encode(&seat(14, 7).unwrap()), "FFFBBBFRRR");
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(seat(127, 7).unwrap().id(), 1023);
+        assert!(seat(128, 0).is_none());
+        assert!(seat(0, 8).is_none());
+        assert!(PlaneLayout::default().seat_from_id(1024).is_none());
+    }
+
+    #[test]
+    fn test_round_trip_every_seat() {
+        for id in 0..1024 {
+            let s = PlaneLayout::default().seat_from_id(id).unwrap();
+            assert_eq!(s.id(), id);
+            assert_eq!(seat(s.row(), s.col()), Some(s));
+            let pass = encode(&s);
+            assert_eq!(pass.len(), 10);
+            assert_eq!(decode(&pass), Ok(s), "{}", pass);
+            assert_eq!(crate::get_id(&PlaneLayout::default(), &pass), Ok(id));
+        }
+    }
+
+    #[test]
+    fn test_not_a_pass() {
+        let err = |pass : &str| decode(pass).unwrap_err();
+        assert_eq!(err("FBFBBFFRL"), PassError::Length { expected: 10, found: 9 });
+        assert_eq!(err("FBFBBFFRLRL"), PassError::Length { expected: 10, found: 11 });
+        assert_eq!(err("FBR"), PassError::Length { expected: 10, found: 3 });
+        assert_eq!(err(""), PassError::Length { expected: 10, found: 0 });
+        assert_eq!(err("XXXXXXXXXX"), PassError::Row { index: 0, c: 'X', expected: FB });
+        assert_eq!(err("FBFBBFRRLR"), PassError::Row { index: 6, c: 'R', expected: FB });
+        assert_eq!(err("FBFBBFFBLR"), PassError::Col { index: 7, c: 'B', expected: LR });
+        assert_eq!(err("RLFBFBBFFB"), PassError::Row { index: 0, c: 'R', expected: FB });
+        assert_eq!(err("fbfbbffrlr"), PassError::Row { index: 0, c: 'f', expected: FB });
+        assert_eq!(err("FBFBBFFRL\u{e9}"), PassError::Col { index: 9, c: '\u{e9}', expected: LR });
+        assert_eq!(err("FBFBBFFRL\u{e9}").to_string(), "character 9: expected L or R, found '\u{e9}'");
+        assert_eq!(err("FBR").to_string(), "expected 10 characters, found 3");
+    }
+}